@@ -1,71 +1,288 @@
-use std::collections::HashMap;
 use aoc_runner_derive::aoc;
-use regex::Regex;
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
 
-#[aoc(day1, part1)]
-fn solve_part1(input: &str) -> u32 {
-    let mut answer = 0;
+/// The tokens that stand for a digit.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Vocabulary {
+    /// Tokens with the digit they stand for, longest token first.
+    tokens: Vec<(String, u32)>,
+}
 
-    let digit_hashmap = HashMap::from([
-        ("1", 1),
-        ("2", 2),
-        ("3", 3),
-        ("4", 4),
-        ("5", 5),
-        ("6", 6),
-        ("7", 7),
-        ("8", 8),
-        ("9", 9),
-    ]);
+impl Vocabulary {
+    pub fn new<S: Into<String>>(
+        tokens: impl IntoIterator<Item = (S, u32)>,
+    ) -> Result<Self, &'static str> {
+        let mut vocabulary: Vec<(String, u32)> = Vec::new();
+        for (token, digit) in tokens {
+            let token = token.into();
+            if token.is_empty() {
+                return Err("Token is empty");
+            }
+            if digit > 9 {
+                return Err("Token must stand for a single digit");
+            }
+            match vocabulary.iter().find(|(known, _)| *known == token) {
+                Some(&(_, known)) if known != digit => {
+                    return Err("Token stands for two different digits")
+                }
+                Some(_) => {}
+                None => vocabulary.push((token, digit)),
+            }
+        }
+
+        vocabulary.sort_by_key(|(token, _)| std::cmp::Reverse(token.len()));
+        Ok(Vocabulary { tokens: vocabulary })
+    }
 
-    let re_first = Regex::new(r"(?<digit>\d).*").unwrap();
-    let re_last = Regex::new(r".*(?<digit>\d)").unwrap();
+    /// The digits 1 to 9, as in part 1.
+    pub fn digits() -> Self {
+        Vocabulary::new((1..=9).map(|digit| (digit.to_string(), digit))).expect("Valid digits")
+    }
+
+    /// The digits 1 to 9 together with a word for each of them, for example
+    /// `["een", "twee", "drie", "vier", "vijf", "zes", "zeven", "acht", "negen"]`.
+    pub fn with_words(words: [&str; 9]) -> Result<Self, &'static str> {
+        let words = words
+            .into_iter()
+            .zip(1..)
+            .map(|(word, digit)| (word.to_string(), digit));
+        Vocabulary::new(Vocabulary::digits().tokens.into_iter().chain(words))
+    }
 
-    for l in input.lines() {
-        let caps_first = re_first.captures(l).unwrap();
-        let caps_last = re_last.captures(l).unwrap();
+    /// The digits and English words for 1 to 9, as in part 2.
+    pub fn english() -> Self {
+        Vocabulary::with_words([
+            "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+        ])
+        .expect("Valid words")
+    }
 
-        let calibration_value = digit_hashmap.get(&caps_first["digit"]).unwrap() * 10 + digit_hashmap.get(&caps_last["digit"]).unwrap();
-        answer += calibration_value;
+    /// The digit of the longest token that starts at byte `i` of the line.
+    fn digit_at(&self, line: &[u8], i: usize) -> Option<u32> {
+        self.tokens
+            .iter()
+            .find(|(token, _)| line[i..].starts_with(token.as_bytes()))
+            .map(|&(_, digit)| digit)
     }
+}
 
-    return answer;
+#[derive(Debug)]
+pub enum CalibrationError {
+    /// The line, counted from 1, has no digit.
+    NoDigit(usize),
+    Io(std::io::Error),
 }
+
+impl Display for CalibrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationError::NoDigit(line) => write!(f, "Line {} has no digit", line),
+            CalibrationError::Io(error) => write!(f, "Failed to read line: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for CalibrationError {}
+
+#[aoc(day1, part1)]
+fn solve_part1(input: &str) -> Result<u32, CalibrationError> {
+    calibration_sum(input.as_bytes(), &Vocabulary::digits())
+}
+
 #[aoc(day1, part2)]
-fn solve_part2(input: &str) -> u32 {
-    let mut answer = 0;
-
-    let digit_hashmap = HashMap::from([
-        ("one", 1),
-        ("two", 2),
-        ("three", 3),
-        ("four", 4),
-        ("five", 5),
-        ("six", 6),
-        ("seven", 7),
-        ("eight", 8),
-        ("nine", 9),
-        ("1", 1),
-        ("2", 2),
-        ("3", 3),
-        ("4", 4),
-        ("5", 5),
-        ("6", 6),
-        ("7", 7),
-        ("8", 8),
-        ("9", 9),
-    ]);
-
-    let re_first = Regex::new(r"(?<digit>\d|one|two|three|four|five|six|seven|eight|nine).*").unwrap();
-    let re_last = Regex::new(r".*(?<digit>\d|one|two|three|four|five|six|seven|eight|nine)").unwrap();
-
-    for l in input.lines() {
-        let caps_first = re_first.captures(l).unwrap();
-        let caps_last = re_last.captures(l).unwrap();
-
-        let calibration_value = digit_hashmap.get(&caps_first["digit"]).unwrap() * 10 + digit_hashmap.get(&caps_last["digit"]).unwrap();
-        answer += calibration_value;
-    }
-
-    return answer;
-}
\ No newline at end of file
+fn solve_part2(input: &str) -> Result<u32, CalibrationError> {
+    calibration_sum(input.as_bytes(), &Vocabulary::english())
+}
+
+/// Sums the calibration values of every line read.
+pub fn calibration_sum(
+    reader: impl BufRead,
+    vocabulary: &Vocabulary,
+) -> Result<u32, CalibrationError> {
+    reader.lines().enumerate().try_fold(0, |sum, (i, line)| {
+        let line = line.map_err(CalibrationError::Io)?;
+        let value = calibration_value(&line, vocabulary).ok_or(CalibrationError::NoDigit(i + 1))?;
+        Ok(sum + value)
+    })
+}
+
+/// The first and last digit of the line combined into a two digit number, where a digit is any
+/// token of the vocabulary. The line is scanned from the front for the first digit and from the
+/// back for the last, so tokens that overlap, like the `eight` and `two` in `eightwo`, are both
+/// found. When several tokens start at the same place, the longest one counts.
+pub fn calibration_value(line: &str, vocabulary: &Vocabulary) -> Option<u32> {
+    let bytes = line.as_bytes();
+
+    let first = (0..bytes.len()).find_map(|i| vocabulary.digit_at(bytes, i))?;
+    let last = (0..bytes.len())
+        .rev()
+        .find_map(|i| vocabulary.digit_at(bytes, i))?;
+    Some(first * 10 + last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Read};
+
+    const EXAMPLE_INPUT_PART1: &str = "1abc2
+pqr3stu8vwx
+a1b2c3d4e5f
+treb7uchet";
+
+    const EXAMPLE_INPUT_PART2: &str = "two1nine
+eightwothree
+abcone2threexyz
+xtwone3four
+4nineeightseven2
+zoneight234
+7pqrstsixteen";
+
+    #[test]
+    fn solve_example_part1() {
+        assert_eq!(solve_part1(EXAMPLE_INPUT_PART1).unwrap(), 142);
+    }
+
+    #[test]
+    fn solve_example_part2() {
+        assert_eq!(solve_part2(EXAMPLE_INPUT_PART2).unwrap(), 281);
+    }
+
+    #[test]
+    fn example_values_part2() {
+        let values: Vec<Option<u32>> = EXAMPLE_INPUT_PART2
+            .lines()
+            .map(|line| calibration_value(line, &Vocabulary::english()))
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![
+                Some(29),
+                Some(83),
+                Some(13),
+                Some(24),
+                Some(42),
+                Some(14),
+                Some(76)
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping_words() {
+        assert_eq!(
+            calibration_value("eightwo", &Vocabulary::english()),
+            Some(82)
+        );
+        assert_eq!(
+            calibration_value("oneight", &Vocabulary::english()),
+            Some(18)
+        );
+        assert_eq!(calibration_value("twone", &Vocabulary::english()), Some(21));
+        assert_eq!(
+            calibration_value("sevenine", &Vocabulary::english()),
+            Some(79)
+        );
+        assert_eq!(
+            calibration_value("eighthree", &Vocabulary::english()),
+            Some(83)
+        );
+        assert_eq!(calibration_value("nine", &Vocabulary::english()), Some(99));
+        assert_eq!(calibration_value("eightwo", &Vocabulary::digits()), None);
+    }
+
+    #[test]
+    fn lines_without_digits() {
+        assert_eq!(calibration_value("", &Vocabulary::english()), None);
+        assert_eq!(calibration_value("abc", &Vocabulary::english()), None);
+        assert_eq!(calibration_value("zero", &Vocabulary::english()), None);
+        assert_eq!(
+            calibration_value("ünï9cødé", &Vocabulary::english()),
+            Some(99)
+        );
+
+        assert!(matches!(
+            solve_part1(EXAMPLE_INPUT_PART2),
+            Err(CalibrationError::NoDigit(2))
+        ));
+        assert!(matches!(
+            calibration_sum("1a\nb2\nabc\n3".as_bytes(), &Vocabulary::digits()),
+            Err(CalibrationError::NoDigit(3))
+        ));
+        assert_eq!(
+            CalibrationError::NoDigit(3).to_string(),
+            "Line 3 has no digit"
+        );
+    }
+
+    #[test]
+    fn read_from_any_reader() {
+        let crlf = EXAMPLE_INPUT_PART2.replace('\n', "\r\n");
+        let reader = BufReader::new(crlf.as_bytes().chain(&b"\nnine"[..]));
+
+        assert_eq!(
+            calibration_sum(reader, &Vocabulary::english()).unwrap(),
+            281 + 99
+        );
+    }
+
+    #[test]
+    fn dutch_words() {
+        let dutch = Vocabulary::with_words([
+            "een", "twee", "drie", "vier", "vijf", "zes", "zeven", "acht", "negen",
+        ])
+        .unwrap();
+
+        assert_eq!(calibration_value("zevenacht", &dutch), Some(78));
+        assert_eq!(calibration_value("achtwee", &dutch), Some(82));
+        assert_eq!(calibration_value("tweeën3vijftig", &dutch), Some(25));
+        assert_eq!(calibration_value("xzesx", &dutch), Some(66));
+        assert_eq!(calibration_value("eightwo", &dutch), None);
+        assert_eq!(
+            calibration_sum("negenendertig\n1eenentwintig".as_bytes(), &dutch).unwrap(),
+            99 + 11
+        );
+    }
+
+    #[test]
+    fn custom_tokens() {
+        let roman =
+            Vocabulary::new([("I", 1), ("II", 2), ("III", 3), ("IV", 4), ("V", 5)]).unwrap();
+
+        // the longest token starting at a place wins, and the last token is the one starting last
+        assert_eq!(calibration_value("xIIx", &roman), Some(21));
+        assert_eq!(calibration_value("IV", &roman), Some(45));
+        assert_eq!(calibration_value("IIIV", &roman), Some(35));
+
+        let zero = Vocabulary::new([("zero", 0), ("0", 0), ("one", 1)]).unwrap();
+        assert_eq!(calibration_value("zerone", &zero), Some(1));
+        assert_eq!(calibration_value("onezero", &zero), Some(10));
+    }
+
+    #[test]
+    fn invalid_vocabularies() {
+        assert_eq!(Vocabulary::new([("", 1)]), Err("Token is empty"));
+        assert_eq!(
+            Vocabulary::new([("ten", 10)]),
+            Err("Token must stand for a single digit")
+        );
+        assert_eq!(
+            Vocabulary::new([("one", 1), ("one", 2)]),
+            Err("Token stands for two different digits")
+        );
+        assert_eq!(
+            Vocabulary::new([("one", 1), ("one", 1)]),
+            Vocabulary::new([("one", 1)])
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error() {
+        assert!(matches!(
+            calibration_sum(&b"1a\n\xff2\n"[..], &Vocabulary::digits()),
+            Err(CalibrationError::Io(_))
+        ));
+    }
+}
@@ -1,22 +1,20 @@
-use std::collections::HashMap;
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum Condition {
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+pub enum Condition {
     Operational,
     Damaged,
     Unknown,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-struct Record {
-    conditions: Vec<Condition>,
-    damaged_springs: Vec<usize>,
+pub struct Record {
+    pub conditions: Vec<Condition>,
+    pub damaged_springs: Vec<usize>,
 }
 
 #[aoc_generator(day12)]
-fn parse_input(input: &str) -> Vec<Record> {
+pub fn parse_input(input: &str) -> Vec<Record> {
     input
         .lines()
         .map(|line| {
@@ -41,136 +39,229 @@ fn parse_input(input: &str) -> Vec<Record> {
         .collect()
 }
 
+impl Record {
+    /// Unfolds the record by repeating it `factor` times, joining the copies of the conditions
+    /// with an unknown spring.
+    pub fn unfold(&self, factor: usize) -> Record {
+        Record {
+            conditions: vec![self.conditions.clone(); factor].join(&Condition::Unknown),
+            damaged_springs: self.damaged_springs.repeat(factor),
+        }
+    }
+}
+
 #[aoc(day12, part1)]
-fn solve_part1(report: &[Record]) -> usize {
-    report
-        .iter()
-        .map(|record| {
-            solver(
-                &record
-                    .conditions
-                    .clone()
-                    .into_iter()
-                    .enumerate()
-                    .filter(|(_, condition)| {
-                        condition == &Condition::Damaged || condition == &Condition::Unknown
-                    })
-                    .collect::<Vec<_>>(),
-                &record.damaged_springs,
-                &mut HashMap::new()
-            )
-        })
-        .sum()
+fn solve_part1(report: &[Record]) -> Option<u128> {
+    total_arrangements(report, 1)
 }
 
 #[aoc(day12, part2)]
-fn solve_part2(report: &[Record]) -> usize {
-    report
-        .iter()
-        .map(|record| {
-            let temp: Vec<Vec<Condition>> =
-                std::iter::repeat(record.conditions.clone()).take(5).collect();
-            Record {
-                conditions: temp.join(&Condition::Unknown),
-                damaged_springs: record.damaged_springs.repeat(5),
+fn solve_part2(report: &[Record]) -> Option<u128> {
+    total_arrangements(report, 5)
+}
+
+/// Sums the arrangements of every record unfolded `factor` times, `None` when the sum does not
+/// fit in a `u128`.
+pub fn total_arrangements(report: &[Record], factor: usize) -> Option<u128> {
+    report.iter().try_fold(0u128, |sum, record| {
+        sum.checked_add(arrangements(record, factor)?)
+    })
+}
+
+/// Counts the arrangements of the record unfolded `factor` times.
+///
+/// Counts are returned as `u128` as they grow exponentially with the factor, and are `None` once
+/// they do not fit in one.
+pub fn arrangements(record: &Record, factor: usize) -> Option<u128> {
+    count_arrangements(&record.unfold(factor))
+}
+
+/// Counts the arrangements of a record with a bottom-up table over (position, group, run length).
+///
+/// After processing a position, `table[group][run]` holds the number of ways in which `group`
+/// groups have been completed and the current run of damaged springs is `run` long. Only the
+/// rows of the previous position are kept, and only the band of groups that can still be
+/// reached is visited, so long unfolded records stay cheap. `None` when a count does not fit in a
+/// `u128`.
+fn count_arrangements(record: &Record) -> Option<u128> {
+    let groups = &record.damaged_springs;
+    let longest = groups.iter().copied().max().unwrap_or(0);
+
+    let mut table = vec![vec![0u128; longest + 1]; groups.len() + 1];
+    let mut next = table.clone();
+    table[0][0] = 1;
+    let (mut first, mut last) = (0, 0);
+
+    for condition in &record.conditions {
+        let reach = (last + 1).min(groups.len());
+        next[first..=reach].iter_mut().for_each(|runs| runs.fill(0));
+
+        for group in first..=last {
+            for run in 0..=longest {
+                let count = table[group][run];
+                if count == 0 {
+                    continue;
+                }
+
+                if condition != &Condition::Damaged {
+                    // operational: either no run in progress, or it closes the current group
+                    if run == 0 {
+                        next[group][0] = next[group][0].checked_add(count)?;
+                    } else if run == groups[group] {
+                        next[group + 1][0] = next[group + 1][0].checked_add(count)?;
+                    }
+                }
+                if condition != &Condition::Operational
+                    && group < groups.len()
+                    && run < groups[group]
+                {
+                    next[group][run + 1] = next[group][run + 1].checked_add(count)?;
+                }
             }
-        })
-        .map(|record| {
-            solver(
-                &record
-                    .conditions
-                    .clone()
-                    .into_iter()
-                    .enumerate()
-                    .filter(|(_, condition)| {
-                        condition == &Condition::Damaged || condition == &Condition::Unknown
-                    })
-                    .collect::<Vec<_>>(),
-                &record.damaged_springs,
-                &mut HashMap::new()
-            )
-        })
-        .sum()
+        }
+
+        std::mem::swap(&mut table, &mut next);
+
+        let is_empty = |group: usize| table[group].iter().all(|&count| count == 0);
+        last = reach;
+        while first < last && is_empty(first) {
+            first += 1;
+        }
+        while last > first && is_empty(last) {
+            last -= 1;
+        }
+    }
+
+    // rows outside the band may hold stale counts from earlier positions
+    let count = |group: usize, run: usize| match (first..=last).contains(&group) {
+        true => table[group][run],
+        false => 0,
+    };
+
+    match groups.last() {
+        Some(&size) => count(groups.len(), 0).checked_add(count(groups.len() - 1, size)),
+        None => Some(count(0, 0)),
+    }
+}
+
+/// Memoised arrangement counts of a single record, used to enumerate and sample the valid
+/// arrangements without generating the invalid ones.
+///
+/// Arrangements are ordered lexicographically with an operational spring before a damaged one.
+pub struct ArrangementTable<'a> {
+    record: &'a Record,
+    /// `counts[position][group]` holds the number of ways to complete the conditions from
+    /// `position` onwards with the groups from `group` onwards, given that the spring before
+    /// `position` does not continue a damaged group.
+    counts: Vec<Vec<u128>>,
 }
 
-fn solver(conditions: &[(usize, Condition)], groups: &[usize], cache: &mut HashMap<(usize, usize), usize>) -> usize {
-    if conditions.is_empty() {
-        // base case
-        return if groups.is_empty() { 1 } else { 0 };
+impl<'a> ArrangementTable<'a> {
+    /// Builds the table, `None` when a count does not fit in a `u128`.
+    pub fn new(record: &'a Record) -> Option<ArrangementTable<'a>> {
+        let n = record.conditions.len();
+        let groups = &record.damaged_springs;
+
+        let mut counts = vec![vec![0; groups.len() + 1]; n + 2];
+        counts[n][groups.len()] = 1;
+        counts[n + 1][groups.len()] = 1;
+
+        for position in (0..n).rev() {
+            for group in 0..=groups.len() {
+                let mut count: u128 = 0;
+                if record.conditions[position] != Condition::Damaged {
+                    count = counts[position + 1][group];
+                }
+                if group < groups.len() && Self::fits(record, position, groups[group]) {
+                    count = count.checked_add(counts[position + groups[group] + 1][group + 1])?;
+                }
+                counts[position][group] = count;
+            }
+        }
+
+        Some(ArrangementTable { record, counts })
     }
-    let current_group_size = match groups.first() {
-        Some(s) => *s,
-        None => {
-            return conditions
+
+    /// Whether a damaged group of `size` springs can start at `position` and be terminated
+    /// directly after.
+    fn fits(record: &Record, position: usize, size: usize) -> bool {
+        let end = position + size;
+        end <= record.conditions.len()
+            && record.conditions[position..end]
                 .iter()
-                .all(|(_, c)| c == &Condition::Unknown)
-                .then_some(1)
-                .unwrap_or(0)
+                .all(|c| c != &Condition::Operational)
+            && record.conditions.get(end) != Some(&Condition::Damaged)
+    }
+
+    /// Total number of valid arrangements.
+    pub fn count(&self) -> u128 {
+        self.counts[0][0]
+    }
+
+    /// The arrangement at `rank` in lexicographic order, if there are that many.
+    pub fn nth(&self, mut rank: u128) -> Option<Vec<Condition>> {
+        if rank >= self.count() {
+            return None;
         }
-    };
 
-    let (index, current_condition) = conditions.first().unwrap();
-
-    match cache.get(&(*index, groups.len())) {
-        Some(&result) => result,
-        None => {
-            let result = match (index, current_condition) {
-                (i, Condition::Damaged) => consume(&conditions, &groups, current_group_size, i, cache),
-                (i, Condition::Unknown) => {
-                    let consumed_sum = consume(&conditions, &groups, current_group_size, i, cache);
-                    let skipped_sum = solver(&conditions[1..], &groups[..], cache);
-                    consumed_sum + skipped_sum
-                }
-                _ => panic!("Conditions should only contain Damaged and Unknown"),
+        let conditions = &self.record.conditions;
+        let groups = &self.record.damaged_springs;
+        let mut arrangement = Vec::with_capacity(conditions.len());
+        let (mut position, mut group) = (0, 0);
+
+        while position < conditions.len() {
+            let operational = match conditions[position] {
+                Condition::Damaged => 0,
+                _ => self.counts[position + 1][group],
             };
-            cache.insert((*index, groups.len()), result);
-            result
+
+            if rank < operational {
+                arrangement.push(Condition::Operational);
+                position += 1;
+            } else {
+                rank -= operational;
+                let size = groups[group];
+                arrangement.extend(std::iter::repeat_n(Condition::Damaged, size));
+                if position + size < conditions.len() {
+                    arrangement.push(Condition::Operational);
+                }
+                position += size + 1;
+                group += 1;
+            }
         }
+
+        Some(arrangement)
     }
-}
 
-fn consume(
-    conditions: &&[(usize, Condition)],
-    groups: &&[usize],
-    current_group_size: usize,
-    i: &usize,
-    cache: &mut HashMap<(usize, usize), usize>,
-) -> usize {
-    if conditions.len() < current_group_size {
-        // cannot consume
-        return 0;
+    /// Iterates over all valid arrangements in lexicographic order.
+    pub fn iter(&self) -> impl Iterator<Item = Vec<Condition>> + '_ {
+        (0..self.count()).map_while(|rank| self.nth(rank))
     }
 
-    if is_continuous(&conditions[0..current_group_size]) {
-        // check not followed by Condition
-        match conditions.get(current_group_size) {
-            None => solver(&conditions[current_group_size..], &groups[1..], cache), // no more conditions
-            Some((j, _)) if j > &(i + &current_group_size) => {
-                // not followed
-                solver(&conditions[current_group_size..], &groups[1..], cache)
-            }
-            Some((j, Condition::Unknown)) if j == &(i + current_group_size) => {
-                // followed by an unknown
-                solver(&conditions[current_group_size + 1..], &groups[1..], cache)
+    /// Picks an arrangement uniformly at random, drawing random bits from `rng`. Returns `None`
+    /// when the record has no valid arrangement.
+    pub fn sample(&self, mut rng: impl FnMut() -> u64) -> Option<Vec<Condition>> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+
+        // Reject the top values that would bias the modulo towards low ranks.
+        let zone = u128::MAX - u128::MAX % count;
+        loop {
+            let random = ((rng() as u128) << 64) | rng() as u128;
+            if random < zone {
+                return self.nth(random % count);
             }
-            _ => 0,
         }
-    } else {
-        0
     }
 }
 
-fn is_continuous(conditions: &[(usize, Condition)]) -> bool {
-    conditions
-        .iter()
-        .tuple_windows()
-        .map(|((a, _), (b, _))| b - a)
-        .all(|delta| delta == 1)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::xorshift;
+    use std::collections::HashMap;
     use Condition::Damaged as D;
     use Condition::Operational as O;
     use Condition::Unknown as U;
@@ -195,89 +286,338 @@ mod tests {
 
     #[test]
     fn solve_example_part1() {
-        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT)), 21)
+        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT)), Some(21))
     }
 
     #[test]
     fn solve_example_part2() {
-        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT)), 525152)
+        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT)), Some(525152))
     }
 
     #[test]
-    fn test_solve() {
-        // base cases
-        assert_eq!(
-            solver(&vec![], &vec![1, 1, 1], &mut HashMap::new()),
-            0,
-            "Empty conditions should return None when not all groups are consumed"
-        );
+    fn test_unfold() {
+        let record = Record {
+            conditions: vec![O, D],
+            damaged_springs: vec![1],
+        };
+
         assert_eq!(
-            solver(&vec![], &vec![], &mut HashMap::new()),
-            1,
-            "Empty conditions should return one when all groups are consumed"
+            record.unfold(3),
+            Record {
+                conditions: vec![O, D, U, O, D, U, O, D],
+                damaged_springs: vec![1, 1, 1],
+            }
         );
+        assert_eq!(record.unfold(1), record);
+    }
+
+    #[test]
+    fn test_arrangements_unfolded() {
+        let report = parse_input(EXAMPLE_INPUT);
 
-        // Only knows
         assert_eq!(
-            solver(&vec![(1, D), (4, D), (7, D)], &vec![1, 1, 1], &mut HashMap::new()),
-            1
+            report
+                .iter()
+                .map(|record| arrangements(record, 5))
+                .collect::<Vec<_>>(),
+            [1, 16384, 1, 16, 2500, 506250].map(Some)
         );
+        assert_eq!(arrangements(&report[0], 1), Some(1));
+        assert_eq!(arrangements(&report[1], 2), Some(32));
+    }
 
-        assert_eq!(solver(&vec![(1, D), (2, D), (7, D)], &vec![1, 1, 1], &mut HashMap::new()), 0);
+    #[test]
+    fn test_arrangements_large_factor() {
+        // `?###????????` grows by a factor of 15 with every copy, which overflows `usize` at 17
+        // copies.
+        let record = &parse_input(EXAMPLE_INPUT)[5];
 
-        assert_eq!(solver(&vec![(1, D), (2, U), (7, D)], &vec![1, 1], &mut HashMap::new()), 1);
+        assert_eq!(arrangements(record, 20), Some(506250 * 15u128.pow(15)));
+        assert_eq!(arrangements(record, 32), Some(10 * 15u128.pow(31)));
+        // 10 * 15^32 no longer fits in a `u128`
+        assert_eq!(arrangements(record, 33), None);
+        assert_eq!(
+            ArrangementTable::new(&record.unfold(32)).map(|table| table.count()),
+            Some(10 * 15u128.pow(31))
+        );
+        assert!(ArrangementTable::new(&record.unfold(33)).is_none());
+        assert_eq!(solve_part1(&vec![record.unfold(32); 12]), None);
+    }
 
-        assert_eq!(solver(&vec![(1, D), (7, D), (8, U)], &vec![1, 1], &mut HashMap::new()), 1);
+    #[test]
+    fn test_count_arrangements() {
+        let count = |line: &str| count_arrangements(&parse_input(line)[0]);
 
+        // base cases
         assert_eq!(
-            solver(
-                &vec![(1, U), (2, U), (5, U), (6, U), (10, U), (11, D), (12, D)],
-                &vec![1, 1, 3], &mut HashMap::new()
-            ),
-            4
+            count("... 1,1,1"),
+            Some(0),
+            "Empty conditions should return zero when not all groups are consumed"
         );
-
         assert_eq!(
-            solver(&vec![(4, U)], &vec![1, 2], &mut HashMap::new()),
-            0
+            count_arrangements(&Record {
+                conditions: vec![O, O],
+                damaged_springs: vec![],
+            }),
+            Some(1),
+            "Empty conditions should return one when all groups are consumed"
         );
 
-        assert_eq!(
-            solver(
-                vec![U, U, U, U, U]
-                    .into_iter()
-                    .enumerate()
+        // Only knowns
+        assert_eq!(count(".#..#..# 1,1,1"), Some(1));
+        assert_eq!(count(".##....# 1,1,1"), Some(0));
+        assert_eq!(count(".#?....# 1,1"), Some(1));
+        assert_eq!(count(".#.....#? 1,1"), Some(1));
+
+        assert_eq!(count(".??..??...?## 1,1,3"), Some(4));
+        assert_eq!(count("....? 1,2"), Some(0));
+        assert_eq!(count("????? 1,2"), Some(3));
+        assert_eq!(count(".??? 1"), Some(3));
+        assert_eq!(count("?###???????? 3,2,1"), Some(10));
+    }
+
+    #[test]
+    fn test_count_arrangements_long_record() {
+        // Far longer than the recursion depth of a top-down solver would allow.
+        let record = &parse_input("#.#.# 1,1,1")[0];
+
+        assert_eq!(arrangements(record, 100_000), Some(1));
+    }
+
+    fn is_valid(arrangement: &[Condition], groups: &[usize]) -> bool {
+        arrangement
+            .split(|c| c == &O)
+            .map(|run| run.len())
+            .filter(|&len| len > 0)
+            .eq(groups.iter().copied())
+    }
+
+    fn brute_force(record: &Record) -> Vec<Vec<Condition>> {
+        let unknowns = record.conditions.iter().filter(|c| c == &&U).count();
+
+        (0..1u32 << unknowns)
+            .map(|mask| {
+                let mut bit = unknowns;
+                record
+                    .conditions
+                    .iter()
+                    .map(|c| match c {
+                        U => {
+                            bit -= 1;
+                            if mask & (1 << bit) == 0 {
+                                O
+                            } else {
+                                D
+                            }
+                        }
+                        &c => c,
+                    })
                     .collect::<Vec<_>>()
-                    .as_slice(),
-                &vec![1, 2],
-                &mut HashMap::new()
-            ),
-            3
-        );
+            })
+            .filter(|arrangement| is_valid(arrangement, &record.damaged_springs))
+            .collect()
+    }
 
+    #[test]
+    fn test_arrangement_iter() {
+        let record = &parse_input(EXAMPLE_INPUT)[0];
+        let table = ArrangementTable::new(record).unwrap();
 
-        assert_eq!(solver(&vec![(1, U), (2, U), (3, U)], &vec![1], &mut HashMap::new()), 3);
+        assert_eq!(table.count(), 1);
+        assert_eq!(
+            table.iter().collect::<Vec<_>>(),
+            vec![vec![D, O, D, O, D, D, D]]
+        );
 
+        let record = &parse_input("??#?? 1,1")[0];
         assert_eq!(
-            solver(
-                vec![U, D, D, D, U, U, U, U, U, U, U, U]
-                    .into_iter()
-                    .enumerate()
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-                &vec![3, 2, 1],
-                    &mut HashMap::new()
-            ),
-            10
+            ArrangementTable::new(record)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![vec![O, O, D, O, D], vec![D, O, D, O, O],]
         );
+
+        assert_eq!(ArrangementTable::new(record).unwrap().nth(2), None);
     }
 
     #[test]
-    fn test_is_continuous() {
-        assert_eq!(is_continuous(&vec![(1, D), (2, U), (3, D)]), true);
-        assert_eq!(
-            is_continuous(&vec![(1, D), (2, U), (3, D), (5, U), (7, D)]),
-            false
+    fn test_arrangement_table_without_arrangements() {
+        let record = &parse_input("#.# 3")[0];
+        let table = ArrangementTable::new(record).unwrap();
+
+        assert_eq!(table.count(), 0);
+        assert_eq!(table.iter().count(), 0);
+        assert_eq!(table.sample(xorshift(1)), None);
+    }
+
+    /// A record of up to 12 conditions and up to 3 groups.
+    fn random_record(rng: &mut impl FnMut() -> u64) -> Record {
+        let length = (rng() % 12 + 1) as usize;
+        let conditions = (0..length)
+            .map(|_| match rng() % 4 {
+                0 => O,
+                1 => D,
+                _ => U,
+            })
+            .collect();
+        let damaged_springs = (0..rng() % 4).map(|_| (rng() % 3 + 1) as usize).collect();
+
+        Record {
+            conditions,
+            damaged_springs,
+        }
+    }
+
+    #[test]
+    fn fuzz_arrangements_against_brute_force() {
+        let mut rng = xorshift(0x2023_1212);
+
+        for _ in 0..500 {
+            let record = random_record(&mut rng);
+            let expected = brute_force(&record);
+            let table = ArrangementTable::new(&record).unwrap();
+
+            assert_eq!(table.count(), expected.len() as u128, "{:?}", record);
+            assert_eq!(
+                arrangements(&record, 1),
+                Some(expected.len() as u128),
+                "{:?}",
+                record
+            );
+            assert_eq!(table.iter().collect::<Vec<_>>(), expected, "{:?}", record);
+        }
+    }
+
+    #[test]
+    fn test_sample_arrangement() {
+        let record = &parse_input(EXAMPLE_INPUT)[5];
+        let table = ArrangementTable::new(record).unwrap();
+        let mut rng = xorshift(42);
+
+        let mut seen = vec![0; table.count() as usize];
+        for _ in 0..1000 {
+            let sample = table.sample(&mut rng).expect("Record has arrangements");
+            assert!(is_valid(&sample, &record.damaged_springs));
+            let rank = table.iter().position(|a| a == sample).unwrap();
+            seen[rank] += 1;
+        }
+
+        // 10 arrangements drawn 1000 times, every one should show up regularly.
+        assert!(seen.iter().all(|&n| n > 50), "{:?}", seen);
+    }
+
+    /// The memoised recursive solver that the DP table replaced, kept so the benchmark can
+    /// compare the two.
+    fn recursive_arrangements(record: &Record, factor: usize) -> u128 {
+        let record = record.unfold(factor);
+        let conditions: Vec<(usize, Condition)> = record
+            .conditions
+            .into_iter()
+            .enumerate()
+            .filter(|(_, condition)| condition != &O)
+            .collect();
+
+        recursive_solver(&conditions, &record.damaged_springs, &mut HashMap::new())
+    }
+
+    fn recursive_solver(
+        conditions: &[(usize, Condition)],
+        groups: &[usize],
+        cache: &mut HashMap<(usize, usize), u128>,
+    ) -> u128 {
+        let Some(&(index, condition)) = conditions.first() else {
+            return groups.is_empty() as u128;
+        };
+        let Some(&size) = groups.first() else {
+            return conditions.iter().all(|(_, c)| c == &U) as u128;
+        };
+
+        if let Some(&count) = cache.get(&(index, groups.len())) {
+            return count;
+        }
+
+        // consume the next group starting here, if it is a run of `size` springs not directly
+        // followed by a damaged one
+        let consumed = match conditions.get(..size) {
+            Some(run) if run.last().map(|&(i, _)| i) == Some(index + size - 1) => {
+                match conditions.get(size) {
+                    Some(&(next, D)) if next == index + size => 0,
+                    Some(&(next, U)) if next == index + size => {
+                        recursive_solver(&conditions[size + 1..], &groups[1..], cache)
+                    }
+                    _ => recursive_solver(&conditions[size..], &groups[1..], cache),
+                }
+            }
+            _ => 0,
+        };
+        let skipped = match condition {
+            U => recursive_solver(&conditions[1..], groups, cache),
+            _ => 0,
+        };
+
+        cache.insert((index, groups.len()), consumed + skipped);
+        consumed + skipped
+    }
+
+    #[test]
+    fn recursive_solver_matches() {
+        let mut rng = xorshift(28);
+
+        for _ in 0..500 {
+            let record = random_record(&mut rng);
+            assert_eq!(
+                arrangements(&record, 2),
+                Some(recursive_arrangements(&record, 2)),
+                "{:?}",
+                record
+            );
+        }
+    }
+
+    /// Times the DP solver against the recursive one on the workloads it was tuned for. Run with
+    /// `cargo test --release day12::tests::bench_count_arrangements -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_count_arrangements() {
+        let time = |name: &str, run: &dyn Fn()| {
+            let start = std::time::Instant::now();
+            run();
+            println!("{}: {:?}", name, start.elapsed());
+        };
+
+        let mut rng = xorshift(0x2023_1212);
+        let records: Vec<Record> = (0..1000).map(|_| random_record(&mut rng)).collect();
+        time("1000 random records, factor 5, recursive", &|| {
+            records.iter().for_each(|record| {
+                std::hint::black_box(recursive_arrangements(record, 5));
+            })
+        });
+        time("1000 random records, factor 5, table", &|| {
+            records.iter().for_each(|record| {
+                std::hint::black_box(arrangements(record, 5));
+            })
+        });
+
+        let record = &parse_input("?###???????? 3,2,1")[0];
+        time(
+            "`?###???????? 3,2,1`, factor 30, 100 times, recursive",
+            &|| {
+                (0..100).for_each(|_| {
+                    std::hint::black_box(recursive_arrangements(record, 30));
+                })
+            },
         );
+        time("`?###???????? 3,2,1`, factor 30, 100 times, table", &|| {
+            (0..100).for_each(|_| {
+                std::hint::black_box(arrangements(record, 30));
+            })
+        });
+
+        // the recursive solver recurses once per group and overflows the stack on this one
+        let record = &parse_input("#.#.# 1,1,1")[0];
+        time("`#.#.# 1,1,1`, factor 5000, table", &|| {
+            std::hint::black_box(arrangements(record, 5000));
+        });
     }
 }
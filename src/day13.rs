@@ -1,10 +1,12 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::fmt::{Debug, Formatter};
 
-type Pattern = Vec<Vec<Tile>>;
+pub type Pattern = Vec<Vec<Tile>>;
+
+const RAGGED: &str = "Pattern rows should all have the same width";
 
 #[derive(Eq, PartialEq, Clone)]
-enum Tile {
+pub enum Tile {
     Ash,
     Rocks,
 }
@@ -35,92 +37,187 @@ impl TryFrom<char> for Tile {
 }
 
 #[aoc_generator(day13)]
-fn parse_input(input: &str) -> Vec<Pattern> {
+pub fn parse_input(input: &str) -> Result<Vec<Pattern>, &'static str> {
     input
         .split("\n\n")
         .map(|pattern| {
-            pattern
+            let pattern: Pattern = pattern
                 .lines()
-                .map(|line| {
-                    line.chars()
-                        .map(|tile| Tile::try_from(tile).unwrap())
-                        .collect()
-                })
-                .collect()
+                .map(|line| line.chars().map(Tile::try_from).collect())
+                .collect::<Result<_, _>>()?;
+
+            match pattern
+                .windows(2)
+                .all(|rows| rows[0].len() == rows[1].len())
+            {
+                true => Ok(pattern),
+                false => Err(RAGGED),
+            }
         })
         .collect()
 }
 
-#[aoc(day13, part1)]
-fn solve_part1(patterns: &[Pattern]) -> usize {
-    let vertical_sum: usize = patterns
-        .iter()
-        .map(|pattern| {
-            splits(pattern)
-                .into_iter()
-                .map(|(a, b)| match is_mirrored(a, b) {
-                    true => a.len(),
-                    false => 0,
-                })
-                .sum::<usize>()
-        })
-        .sum();
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Axis {
+    /// The mirror line lies between two rows.
+    Row,
+    /// The mirror line lies between two columns.
+    Column,
+}
 
-    let horizontal_sum: usize = patterns
-        .iter()
-        .map(|pattern| {
-            splits(&transpose(pattern))
-                .into_iter()
-                .map(|(a, b)| match is_mirrored(a, b) {
-                    true => a.len(),
-                    false => 0,
-                })
-                .sum::<usize>()
-        })
-        .sum();
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Reflection {
+    pub axis: Axis,
+    /// Number of rows above, or columns left of, the mirror line.
+    pub index: usize,
+}
+
+impl Reflection {
+    pub fn score(&self) -> usize {
+        match self.axis {
+            Axis::Row => 100 * self.index,
+            Axis::Column => self.index,
+        }
+    }
+}
+
+/// A mirror line together with the tiles, as (row, column), that have to be flipped for it to
+/// appear. The flips are reported on the top or left side of the mirror line.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Repair {
+    pub reflection: Reflection,
+    pub flips: Vec<(usize, usize)>,
+}
 
-    100 * vertical_sum + horizontal_sum
+#[aoc(day13, part1)]
+fn solve_part1(patterns: &[Pattern]) -> Result<usize, &'static str> {
+    score(patterns, 0)
 }
 
 #[aoc(day13, part2)]
-fn solve_part2(patterns: &[Pattern]) -> usize {
-    let vertical_sum: usize = patterns
-        .iter()
-        .map(|pattern| {
-            splits(pattern)
-                .into_iter()
-                .map(|(a, b)| match is_mirrored_smudge(a, b) {
-                    true => a.len(),
-                    false => 0,
-                })
-                .sum::<usize>()
-        })
-        .sum();
+fn solve_part2(patterns: &[Pattern]) -> Result<usize, &'static str> {
+    score(patterns, 1)
+}
 
-    let horizontal_sum: usize = patterns
-        .iter()
-        .map(|pattern| {
-            splits(&transpose(pattern))
-                .into_iter()
-                .map(|(a, b)| match is_mirrored_smudge(a, b) {
-                    true => a.len(),
-                    false => 0,
-                })
-                .sum::<usize>()
-        })
-        .sum();
+/// Sums the scores of the mirror lines that appear when exactly `smudges` tiles are flipped.
+pub fn score(patterns: &[Pattern], smudges: u32) -> Result<usize, &'static str> {
+    patterns.iter().try_fold(0, |sum, pattern| {
+        let repairs = repairs(pattern, smudges)?;
+        Ok(sum
+            + repairs
+                .iter()
+                .map(|repair| repair.reflection.score())
+                .sum::<usize>())
+    })
+}
 
-    100 * vertical_sum + horizontal_sum
+/// All mirror lines of the pattern.
+pub fn reflections(pattern: &Pattern) -> Result<Vec<Reflection>, &'static str> {
+    Ok(repairs(pattern, 0)?
+        .into_iter()
+        .map(|repair| repair.reflection)
+        .collect())
 }
 
-fn transpose<T>(v: &[Vec<T>]) -> Vec<Vec<T>>
-where
-    T: Clone,
-{
-    assert!(!v.is_empty());
-    (0..v[0].len())
-        .map(|i| v.iter().map(|inner| inner[i].clone()).collect::<Vec<T>>())
-        .collect()
+/// All mirror lines that appear when exactly `smudges` tiles of the pattern are flipped.
+pub fn repairs(pattern: &Pattern, smudges: u32) -> Result<Vec<Repair>, &'static str> {
+    mirror_lines(pattern, move |distance| distance == smudges)
+}
+
+/// The new mirror lines that need the fewest flips to appear. Empty when every possible line is
+/// already a mirror line.
+pub fn minimal_repairs(pattern: &Pattern) -> Result<Vec<Repair>, &'static str> {
+    let candidates = mirror_lines(pattern, |distance| distance > 0)?;
+    let fewest = candidates.iter().map(|repair| repair.flips.len()).min();
+
+    Ok(candidates
+        .into_iter()
+        .filter(|repair| Some(repair.flips.len()) == fewest)
+        .collect())
+}
+
+/// A pattern encoded as one bitmask per row and per column, with a set bit for every rock.
+#[derive(Debug, Eq, PartialEq)]
+struct Bitmasks {
+    /// Bit `c` of `rows[r]` is the tile at row `r`, column `c`.
+    rows: Vec<u64>,
+    /// Bit `r` of `columns[c]` is the tile at row `r`, column `c`.
+    columns: Vec<u64>,
+}
+
+impl TryFrom<&Pattern> for Bitmasks {
+    type Error = &'static str;
+
+    fn try_from(pattern: &Pattern) -> Result<Self, Self::Error> {
+        let width = pattern.first().map_or(0, Vec::len);
+        if pattern.iter().any(|row| row.len() != width) {
+            return Err(RAGGED);
+        }
+        if pattern.len() > 64 || width > 64 {
+            return Err("Pattern does not fit in 64 bit masks");
+        }
+
+        let mut rows = vec![0; pattern.len()];
+        let mut columns = vec![0; width];
+        for (r, row) in pattern.iter().enumerate() {
+            for (c, tile) in row.iter().enumerate() {
+                if tile == &Tile::Rocks {
+                    rows[r] |= 1 << c;
+                    columns[c] |= 1 << r;
+                }
+            }
+        }
+
+        Ok(Bitmasks { rows, columns })
+    }
+}
+
+/// Finds the mirror lines for which the number of tiles that differ from their mirror image
+/// matches `predicate`.
+fn mirror_lines(
+    pattern: &Pattern,
+    predicate: impl Fn(u32) -> bool + Copy,
+) -> Result<Vec<Repair>, &'static str> {
+    let masks = Bitmasks::try_from(pattern)?;
+
+    let rows = mirror_splits(&masks.rows, predicate).map(|(index, flips)| Repair {
+        reflection: Reflection {
+            axis: Axis::Row,
+            index,
+        },
+        flips,
+    });
+
+    let columns = mirror_splits(&masks.columns, predicate).map(|(index, flips)| Repair {
+        reflection: Reflection {
+            axis: Axis::Column,
+            index,
+        },
+        flips: flips
+            .into_iter()
+            .map(|(column, row)| (row, column))
+            .collect(),
+    });
+
+    Ok(rows.chain(columns).collect())
+}
+
+/// Finds the splits of `lines` whose number of bits that differ from their mirror image matches
+/// `predicate`, as the split index with the (line, bit) of every differing bit before the split.
+fn mirror_splits<'a>(
+    lines: &'a [u64],
+    predicate: impl Fn(u32) -> bool + 'a,
+) -> impl Iterator<Item = (usize, Vec<(usize, usize)>)> + 'a {
+    splits(lines)
+        .into_iter()
+        .filter(move |(a, b)| predicate(distance(a, b)))
+        .map(|(a, b)| {
+            let tiles = differences(a, b)
+                .into_iter()
+                .map(|(offset, bit)| (a.len() - 1 - offset, bit))
+                .collect();
+            (a.len(), tiles)
+        })
 }
 
 fn splits<T>(iter: &[T]) -> Vec<(&[T], &[T])> {
@@ -132,17 +229,29 @@ fn splits<T>(iter: &[T]) -> Vec<(&[T], &[T])> {
         .collect()
 }
 
-fn is_mirrored(a: &[Vec<Tile>], b: &[Vec<Tile>]) -> bool {
-    a.iter().rev().zip(b).all(|(row_a, row_b)| row_a == row_b)
+/// Number of bits that differ between `a` mirrored and `b`.
+fn distance(a: &[u64], b: &[u64]) -> u32 {
+    a.iter()
+        .rev()
+        .zip(b)
+        .map(|(line_a, line_b)| (line_a ^ line_b).count_ones())
+        .sum()
 }
 
-fn is_mirrored_smudge(a: &[Vec<Tile>], b: &[Vec<Tile>]) -> bool {
-    match a.iter().rev().zip(b).map(|(row_a, row_b)|
-        row_a.iter().zip(row_b).filter(|(i, j)| i != j).count()
-    ).sum() {
-        1 => true,
-        _ => false,
-    }
+/// Lists the bits that differ between `a` mirrored and `b`, as (distance from the mirror line,
+/// bit).
+fn differences(a: &[u64], b: &[u64]) -> Vec<(usize, usize)> {
+    a.iter()
+        .rev()
+        .zip(b)
+        .enumerate()
+        .flat_map(|(offset, (line_a, line_b))| {
+            let diff = line_a ^ line_b;
+            (0..64)
+                .filter(move |bit| diff & (1 << bit) != 0)
+                .map(move |bit| (offset, bit))
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -168,48 +277,41 @@ mod tests {
 #....#..#";
 
     #[test]
-    fn test_is_mirrored() {
-        assert_eq!(
-            is_mirrored(
-                vec![vec![Rocks, Ash, Rocks, Ash]].as_slice(),
-                vec![vec![Rocks, Ash, Rocks, Ash]].as_slice(),
-            ),
-            true
-        );
+    fn test_bitmasks() {
+        let pattern = vec![vec![Rocks, Ash, Ash], vec![Ash, Rocks, Rocks]];
 
         assert_eq!(
-            is_mirrored(
-                vec![vec![Rocks, Ash, Rocks, Rocks]].as_slice(),
-                vec![vec![Rocks, Ash, Rocks, Ash]].as_slice(),
-            ),
-            false
+            Bitmasks::try_from(&pattern),
+            Ok(Bitmasks {
+                rows: vec![0b001, 0b110],
+                columns: vec![0b01, 0b10, 0b10],
+            })
         );
+    }
 
-        assert_eq!(
-            is_mirrored(
-                vec![vec![Ash, Rocks, Ash, Rocks], vec![Rocks, Ash, Rocks, Ash],].as_slice(),
-                vec![vec![Rocks, Ash, Rocks, Ash]].as_slice(),
-            ),
-            true
-        );
+    #[test]
+    fn test_differences() {
+        assert_eq!(differences(&[0b0101], &[0b0101]), vec![]);
 
-        assert_eq!(
-            is_mirrored(
-                vec![vec![Ash, Rocks, Ash, Rocks]].as_slice(),
-                vec![vec![Ash, Rocks, Ash, Rocks], vec![Rocks, Ash, Rocks, Ash],].as_slice()
-            ),
-            true
-        );
+        assert_eq!(differences(&[0b1101], &[0b0101]), vec![(0, 3)]);
+
+        assert_eq!(differences(&[0b1010, 0b0101], &[0b0101]), vec![]);
+
+        assert_eq!(differences(&[0b1010], &[0b1010, 0b0101]), vec![]);
 
         assert_eq!(
-            is_mirrored(
-                vec![vec![Rocks, Ash, Rocks, Ash], vec![Ash, Rocks, Ash, Rocks],].as_slice(),
-                vec![vec![Ash, Rocks, Ash, Rocks], vec![Rocks, Ash, Rocks, Ash],].as_slice()
-            ),
-            true
+            differences(&[0b0101, 0b1010], &[0b1011, 0b0100]),
+            vec![(0, 0), (1, 0)]
         );
     }
 
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance(&[0b0101, 0b1010], &[0b1010, 0b0101]), 0);
+        assert_eq!(distance(&[0b0101, 0b1010], &[0b1011, 0b0100]), 2);
+        assert_eq!(distance(&[0b1111], &[0b0000, 0b1111]), 4);
+    }
+
     #[test]
     fn test_split_iter() {
         let stuff = &[1, 2, 3, 4];
@@ -225,23 +327,147 @@ mod tests {
     }
 
     #[test]
-    fn test_transpose() {
-        let matrix = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+    fn solve_example_part1() {
+        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT).unwrap()), Ok(405))
+    }
 
-        let transposed_matrix = transpose(&matrix);
+    #[test]
+    fn solve_example_part2() {
+        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT).unwrap()), Ok(400))
+    }
 
-        let expected_transposed_matrix = vec![vec![1, 4, 7], vec![2, 5, 8], vec![3, 6, 9]];
+    #[test]
+    fn example_reflections() {
+        let patterns = parse_input(EXAMPLE_INPUT).unwrap();
 
-        assert_eq!(transposed_matrix, expected_transposed_matrix);
+        assert_eq!(
+            reflections(&patterns[0]),
+            Ok(vec![Reflection {
+                axis: Axis::Column,
+                index: 5
+            }])
+        );
+        assert_eq!(
+            reflections(&patterns[1]),
+            Ok(vec![Reflection {
+                axis: Axis::Row,
+                index: 4
+            }])
+        );
     }
 
     #[test]
-    fn solve_example_part1() {
-        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT)), 405)
+    fn example_smudges() {
+        let patterns = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(
+            repairs(&patterns[0], 1),
+            Ok(vec![Repair {
+                reflection: Reflection {
+                    axis: Axis::Row,
+                    index: 3
+                },
+                flips: vec![(0, 0)],
+            }])
+        );
+        assert_eq!(
+            repairs(&patterns[1], 1),
+            Ok(vec![Repair {
+                reflection: Reflection {
+                    axis: Axis::Row,
+                    index: 1
+                },
+                flips: vec![(0, 4)],
+            }])
+        );
     }
 
     #[test]
-    fn solve_example_part2() {
-        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT)), 400)
+    fn smudge_left_of_column_reflection() {
+        let pattern = &parse_input("#..#.\n.##..\n#####").unwrap()[0];
+
+        assert_eq!(
+            repairs(pattern, 1),
+            Ok(vec![Repair {
+                reflection: Reflection {
+                    axis: Axis::Column,
+                    index: 4
+                },
+                flips: vec![(0, 3)],
+            }])
+        );
+    }
+
+    #[test]
+    fn example_minimal_repairs() {
+        let patterns = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(minimal_repairs(&patterns[0]), repairs(&patterns[0], 1));
+        assert_eq!(minimal_repairs(&patterns[1]), repairs(&patterns[1], 1));
+    }
+
+    #[test]
+    fn minimal_repairs_with_ties() {
+        let pattern = &parse_input("#...\n.##.").unwrap()[0];
+
+        assert_eq!(
+            minimal_repairs(pattern),
+            Ok(vec![
+                Repair {
+                    reflection: Reflection {
+                        axis: Axis::Column,
+                        index: 2
+                    },
+                    flips: vec![(0, 0)],
+                },
+                Repair {
+                    reflection: Reflection {
+                        axis: Axis::Column,
+                        index: 3
+                    },
+                    flips: vec![(1, 2)],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn minimal_repairs_without_new_lines() {
+        let pattern = &parse_input("##\n##").unwrap()[0];
+
+        assert_eq!(reflections(pattern).map(|lines| lines.len()), Ok(2));
+        assert_eq!(minimal_repairs(pattern), Ok(vec![]));
+    }
+
+    #[test]
+    fn score_for_any_smudge_count() {
+        let patterns = parse_input("#...\n.##.").unwrap();
+
+        assert_eq!(score(&patterns, 0), Ok(0));
+        assert_eq!(score(&patterns, 1), Ok(2 + 3));
+        assert_eq!(score(&patterns, 2), Ok(1));
+        assert_eq!(score(&patterns, 3), Ok(100));
+        assert_eq!(score(&patterns, 4), Ok(0));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert_eq!(parse_input("#.\n#"), Err(RAGGED));
+        assert_eq!(parse_input("#.\n.#\n\n#..\n#."), Err(RAGGED));
+        assert_eq!(parse_input("#.\n.x"), Err("Unknown tile"));
+
+        let wide = vec![vec![Ash; 65]; 2];
+        assert_eq!(
+            reflections(&wide),
+            Err("Pattern does not fit in 64 bit masks")
+        );
+        assert_eq!(
+            score(&[vec![vec![Rocks, Ash], vec![Rocks]]], 0),
+            Err(RAGGED)
+        );
+        assert_eq!(
+            reflections(&vec![vec![Rocks; 64]; 64]).map(|lines| lines.len()),
+            Ok(126)
+        );
     }
 }
@@ -1,78 +1,492 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::fmt::{Display, Formatter};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
 
+/// Splits the initialization sequence into its steps. Newlines are ignored, as the puzzle
+/// specifies, and any non-ASCII character is rejected since HASH works on ASCII codes.
 #[aoc_generator(day15)]
-fn parse_input(input: &str) -> Vec<String> {
-    input.split(',').map(|s| String::from(s)).collect()
+fn parse_input(input: &str) -> Result<Vec<Step>, &'static str> {
+    if !input.is_ascii() {
+        return Err("Initialization sequence should only contain ASCII characters");
+    }
+
+    input
+        .trim()
+        .split(',')
+        .map(|s| {
+            let step: String = s.chars().filter(|&c| c != '\n' && c != '\r').collect();
+            Step::try_from(step.as_str())
+        })
+        .collect()
 }
 
+/// Part 1 hashes the steps as written, which is exactly how they are displayed as every part
+/// of a step is parsed from its text without normalising it.
 #[aoc(day15, part1)]
-fn solve_part1(input: &[String]) -> usize {
-    input.iter().map(|s| hash(s)).sum()
+fn solve_part1(input: &[Step]) -> usize {
+    input.iter().map(|step| hash(&step.to_string())).sum()
 }
 
-#[aoc(day15, part2)]
-fn solve_part2(input: &[String]) -> usize {
-    let mut boxes: Vec<Vec<(&str, u8)>> = vec![vec![]; 256];
-
-    input.iter().for_each(|s| match s.chars().last() {
-        Some('-') => {
-            let label = &s[..s.len() - 1];
-            let index = hash(label);
-            boxes[index]
-                .iter()
-                .position(|(l, _)| &label == l)
-                .map(|pos| boxes[index].remove(pos));
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Step {
+    Remove(String),
+    Insert(String, u8),
+}
+
+impl Step {
+    pub fn label(&self) -> &str {
+        match self {
+            Step::Remove(label) | Step::Insert(label, _) => label,
+        }
+    }
+}
+
+impl TryFrom<&str> for Step {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let step = match value.split_once('=') {
+            Some((label, focal)) => Step::Insert(
+                label.to_string(),
+                match focal.as_bytes() {
+                    &[digit @ b'1'..=b'9'] => digit - b'0',
+                    _ => return Err("Focal length should be a digit from 1 to 9"),
+                },
+            ),
+            None => Step::Remove(
+                value
+                    .strip_suffix('-')
+                    .ok_or("Step should contain '=' or end with '-'")?
+                    .to_string(),
+            ),
+        };
+
+        match step.label().chars().all(|c| c.is_ascii_alphabetic()) && !step.label().is_empty() {
+            true => Ok(step),
+            false => Err("Label should consist of letters"),
         }
-        _ => {
-            let (label, lens) = s.split_once('=').unwrap();
-            let index = hash(label);
-            let focal: u8 = lens.parse().unwrap();
-            boxes[index]
-                .iter()
-                .position(|(l, _)| &label == l)
-                .map(|pos| boxes[index][pos] = (label, focal))
-                .unwrap_or_else(|| boxes[index].push((label, focal)))
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Remove(label) => write!(f, "{}-", label),
+            Step::Insert(label, focal) => write!(f, "{}={}", label, focal),
         }
-    });
-
-    boxes
-        .iter()
-        .enumerate()
-        .flat_map(|(i, b)| {
-            b.iter()
-                .enumerate()
-                .map(move |(j, &(_, lens))| (i + 1) * (j + 1) * (lens as usize))
-        })
-        .sum()
+    }
+}
+
+/// The 256 boxes of the HASHMAP procedure, each holding its lenses as (label, focal length) in
+/// order.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct LensBoxes {
+    boxes: Vec<Vec<(String, u8)>>,
+}
+
+impl LensBoxes {
+    pub fn new() -> Self {
+        LensBoxes {
+            boxes: vec![vec![]; 256],
+        }
+    }
+
+    pub fn apply(&mut self, step: &Step) {
+        let lenses = &mut self.boxes[hash(step.label())];
+        let position = lenses.iter().position(|(l, _)| l == step.label());
+
+        match (step, position) {
+            (Step::Remove(_), Some(pos)) => {
+                lenses.remove(pos);
+            }
+            (Step::Remove(_), None) => {}
+            (Step::Insert(_, focal), Some(pos)) => lenses[pos].1 = *focal,
+            (Step::Insert(label, focal), None) => lenses.push((label.clone(), *focal)),
+        }
+    }
+
+    pub fn focusing_power(&self) -> usize {
+        self.boxes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, b)| {
+                b.iter()
+                    .enumerate()
+                    .map(move |(j, &(_, lens))| (i + 1) * (j + 1) * (lens as usize))
+            })
+            .sum()
+    }
+}
+
+impl Default for LensBoxes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Prints the non-empty boxes like the walkthrough in the puzzle text, one line per box.
+impl Display for LensBoxes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, lenses) in self.boxes.iter().enumerate().filter(|(_, b)| !b.is_empty()) {
+            write!(f, "Box {}:", i)?;
+            for (label, focal) in lenses {
+                write!(f, " [{} {}]", label, focal)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
 }
 
-fn hash(s: &str) -> usize {
-    s.chars().fold(0, |val, c| ((val + c as usize) * 17) % 256)
+#[aoc(day15, part2)]
+fn solve_part2(input: &[Step]) -> usize {
+    let mut boxes = LensBoxes::new();
+    input.iter().for_each(|step| boxes.apply(step));
+    boxes.focusing_power()
+}
+
+pub fn hash(s: &str) -> usize {
+    hash_bytes(s.as_bytes())
+}
+
+/// The HASH algorithm over raw bytes.
+pub fn hash_bytes(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |val, &c| hash_step(val, c))
+}
+
+fn hash_step(val: usize, c: u8) -> usize {
+    ((val + c as usize) * 17) % 256
+}
+
+/// HASH as a [`Hasher`], so it can back a [`HashMap`](std::collections::HashMap) with
+/// [`BuildHashHasher`]. With only 256 possible values it is meant for experiments, not for
+/// large maps.
+///
+/// Note that `str` and `[u8]` keys feed extra bytes to the hasher (a terminator or a length
+/// prefix), use [`Label`] keys to get exactly the HASH of the label.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HashHasher {
+    state: usize,
+}
+
+impl Hasher for HashHasher {
+    fn finish(&self) -> u64 {
+        self.state as u64
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.state = bytes.iter().fold(self.state, |val, &c| hash_step(val, c));
+    }
+}
+
+pub type BuildHashHasher = BuildHasherDefault<HashHasher>;
+
+/// A lens label that hashes as its bytes only, so [`HashHasher`] finishes with its box number.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Label(pub String);
+
+impl Hash for Label {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(self.0.as_bytes())
+    }
+}
+
+/// Multiplicative inverse of 17 modulo 256, used to undo the last step of HASH.
+const INVERSE_17: usize = 241;
+
+/// Generates all lowercase labels of `length` letters that HASH to `target`.
+///
+/// Every prefix of `length - 1` letters is tried, and the last letter is solved for directly by
+/// undoing the final HASH step, so each prefix yields at most one label. Fails when there are
+/// more prefixes than fit in a `usize`.
+pub fn collisions(target: u8, length: usize) -> Result<impl Iterator<Item = String>, &'static str> {
+    let prefixes = match length {
+        0 => 0,
+        _ => u32::try_from(length - 1)
+            .ok()
+            .and_then(|exponent| 26usize.checked_pow(exponent))
+            .ok_or("Too many labels of that length to enumerate")?,
+    };
+
+    Ok((0..prefixes).filter_map(move |mut index| {
+        let mut label: Vec<u8> = (1..length)
+            .map(|_| {
+                let letter = b'a' + (index % 26) as u8;
+                index /= 26;
+                letter
+            })
+            .collect();
+
+        let last = (target as usize * INVERSE_17 + 256 - hash_bytes(&label)) % 256;
+        let last = u8::try_from(last).ok().filter(u8::is_ascii_lowercase)?;
+        label.push(last);
+
+        String::from_utf8(label).ok()
+    }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+    use std::hash::BuildHasher;
 
     const EXAMPLE_INPUT: &str = "rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7";
 
+    fn steps(steps: &[&str]) -> Vec<Step> {
+        steps.iter().map(|&s| Step::try_from(s).unwrap()).collect()
+    }
+
     #[test]
     fn parse_example() {
+        let parsed = parse_input(EXAMPLE_INPUT).unwrap();
+
         assert_eq!(
-            parse_input(EXAMPLE_INPUT),
-            vec![
+            parsed,
+            steps(&[
                 "rn=1", "cm-", "qp=3", "cm=2", "qp-", "pc=4", "ot=9", "ab=5", "pc-", "pc=6", "ot=7"
-            ]
-        )
+            ])
+        );
+        assert_eq!(parsed[0], Step::Insert("rn".to_string(), 1));
+        assert_eq!(parsed[1], Step::Remove("cm".to_string()));
+    }
+
+    #[test]
+    fn parse_input_with_trailing_newline() {
+        let expected = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(
+            parse_input(&format!("{}\n", EXAMPLE_INPUT)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_input(&format!("{}\r\n", EXAMPLE_INPUT)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            solve_part1(&parse_input(&format!("{}\n", EXAMPLE_INPUT)).unwrap()),
+            1320
+        );
+        assert_eq!(
+            solve_part2(&parse_input(&format!("{}\n", EXAMPLE_INPUT)).unwrap()),
+            145
+        );
+    }
+
+    #[test]
+    fn parse_input_ignores_newlines() {
+        assert_eq!(
+            parse_input("rn=1,c\nm-,\nqp=3").unwrap(),
+            steps(&["rn=1", "cm-", "qp=3"])
+        );
+    }
+
+    #[test]
+    fn parse_input_rejects_non_ascii() {
+        assert!(parse_input("rn=1,cé-").is_err());
+    }
+
+    #[test]
+    fn parse_input_rejects_invalid_steps() {
+        assert_eq!(
+            parse_input("rn=1,cm"),
+            Err("Step should contain '=' or end with '-'")
+        );
+        assert_eq!(
+            parse_input("rn=1,qp=0"),
+            Err("Focal length should be a digit from 1 to 9")
+        );
+        assert_eq!(
+            parse_input("rn=+1"),
+            Err("Focal length should be a digit from 1 to 9")
+        );
+        assert_eq!(
+            parse_input("rn=1,,qp-"),
+            Err("Step should contain '=' or end with '-'")
+        );
+    }
+
+    #[test]
+    fn part1_hashes_the_raw_text() {
+        for input in [EXAMPLE_INPUT, "rn=1,c\nm-,\nqp=3", "HASH=9,ab-"] {
+            let raw: usize = input.replace('\n', "").split(',').map(hash).sum();
+            assert_eq!(solve_part1(&parse_input(input).unwrap()), raw, "{}", input);
+        }
     }
 
     #[test]
     fn solve_example_part1() {
-        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT)), 1320)
+        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT).unwrap()), 1320)
     }
 
     #[test]
     fn solve_example_part2() {
-        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT)), 145)
+        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT).unwrap()), 145)
+    }
+
+    #[test]
+    fn parse_steps() {
+        assert_eq!(
+            Step::try_from("rn=1"),
+            Ok(Step::Insert("rn".to_string(), 1))
+        );
+        assert_eq!(Step::try_from("cm-"), Ok(Step::Remove("cm".to_string())));
+
+        assert!(Step::try_from("rn").is_err());
+        assert!(Step::try_from("rn=").is_err());
+        assert!(Step::try_from("rn=0").is_err());
+        assert!(Step::try_from("rn=10").is_err());
+        assert!(Step::try_from("=1").is_err());
+        assert!(Step::try_from("-").is_err());
+        assert!(Step::try_from("r-n-").is_err());
+        assert!(Step::try_from("r1=1").is_err());
+        assert!(Step::try_from("rn=+1").is_err());
+        assert!(Step::try_from("rn=01").is_err());
+        assert!(Step::try_from("rn= 1").is_err());
+    }
+
+    #[test]
+    fn display_steps() {
+        for (step, text) in parse_input(EXAMPLE_INPUT)
+            .unwrap()
+            .iter()
+            .zip(EXAMPLE_INPUT.split(','))
+        {
+            assert_eq!(step.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn example_walkthrough() {
+        let mut boxes = LensBoxes::new();
+        let walkthrough: String = parse_input(EXAMPLE_INPUT)
+            .unwrap()
+            .iter()
+            .map(|step| {
+                boxes.apply(step);
+                format!("After \"{}\":\n{}\n", step, boxes)
+            })
+            .collect();
+
+        assert_eq!(
+            walkthrough,
+            r#"After "rn=1":
+Box 0: [rn 1]
+
+After "cm-":
+Box 0: [rn 1]
+
+After "qp=3":
+Box 0: [rn 1]
+Box 1: [qp 3]
+
+After "cm=2":
+Box 0: [rn 1] [cm 2]
+Box 1: [qp 3]
+
+After "qp-":
+Box 0: [rn 1] [cm 2]
+
+After "pc=4":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4]
+
+After "ot=9":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9]
+
+After "ab=5":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9] [ab 5]
+
+After "pc-":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5]
+
+After "pc=6":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5] [pc 6]
+
+After "ot=7":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 7] [ab 5] [pc 6]
+
+"#
+        );
+        assert_eq!(boxes.focusing_power(), 145);
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash("HASH"), 52);
+        assert_eq!(hash_bytes(b"HASH"), 52);
+        assert_eq!(hash("rn"), 0);
+        assert_eq!(hash("qp"), 1);
+        assert_eq!(hash("pc"), 3);
+    }
+
+    #[test]
+    fn hasher_matches_hash() {
+        let build = BuildHashHasher::default();
+
+        for label in ["rn", "cm", "qp", "pc", "ot", "ab", "HASH"] {
+            assert_eq!(build.hash_one(Label(label.to_string())), hash(label) as u64);
+        }
+    }
+
+    #[test]
+    fn hasher_backs_hashmap() {
+        let mut lenses: HashMap<Label, u8, BuildHashHasher> = HashMap::default();
+
+        for step in parse_input(EXAMPLE_INPUT).unwrap() {
+            match step {
+                Step::Remove(label) => lenses.remove(&Label(label)),
+                Step::Insert(label, focal) => lenses.insert(Label(label), focal),
+            };
+        }
+
+        assert_eq!(lenses.len(), 5);
+        assert_eq!(lenses.get(&Label("ot".to_string())), Some(&7));
+        assert_eq!(lenses.get(&Label("pc".to_string())), Some(&6));
+    }
+
+    #[test]
+    fn find_collisions() {
+        let labels = collisions(0, 2).unwrap().collect::<Vec<_>>();
+
+        assert!(labels.contains(&"rn".to_string()));
+        assert!(labels.contains(&"cm".to_string()));
+        assert!(labels
+            .iter()
+            .all(|label| label.len() == 2 && hash(label) == 0));
+
+        for target in 0..=255 {
+            assert!(collisions(target, 3)
+                .unwrap()
+                .all(|label| hash(&label) == target as usize));
+        }
+
+        // every label of three letters is found for its own box
+        assert_eq!(
+            (0..=255)
+                .map(|target| collisions(target, 3).unwrap().count())
+                .sum::<usize>(),
+            26usize.pow(3)
+        );
+
+        assert_eq!(collisions(0, 0).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn collisions_of_long_labels() {
+        assert_eq!(
+            collisions(0, 14).unwrap().next().map(|label| hash(&label)),
+            Some(0)
+        );
+        assert_eq!(
+            collisions(0, 15).err(),
+            Some("Too many labels of that length to enumerate")
+        );
+        assert!(collisions(0, usize::MAX).is_err());
     }
 }
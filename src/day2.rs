@@ -1,93 +1,133 @@
-use std::cmp;
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::cmp;
+use std::collections::HashMap;
+
+/// Number of cubes of every colour.
+pub type Bag = HashMap<String, u32>;
 
 #[derive(PartialEq, Debug)]
-struct Game {
-    id: u32,
-    subsets: Vec<Vec<CubeCount>>,
+pub struct Game {
+    pub id: u32,
+    pub subsets: Vec<Vec<CubeCount>>,
 }
 
 #[derive(PartialEq, Debug)]
-enum Color {
-    RED,
-    GREEN,
-    BLUE,
-}
+pub struct CubeCount(pub String, pub u32);
 
+/// Cubes of a colour shown in a draw that the bag does not hold enough of.
 #[derive(PartialEq, Debug)]
-struct CubeCount(Color, u32);
+pub struct Violation {
+    /// Index of the draw in the game.
+    pub draw: usize,
+    pub colour: String,
+    pub count: u32,
+    /// Number of cubes of the colour in the bag, 0 when the bag does not have the colour.
+    pub limit: u32,
+}
+
+impl Game {
+    /// The fewest cubes of every colour shown that make the game possible.
+    pub fn minimum_bag(&self) -> Bag {
+        self.subsets
+            .iter()
+            .flatten()
+            .fold(Bag::new(), |mut bag, CubeCount(colour, n)| {
+                let max = bag.entry(colour.clone()).or_default();
+                *max = cmp::max(*max, *n);
+                bag
+            })
+    }
+
+    /// Every draw that shows more cubes of a colour than the bag holds.
+    pub fn violations(&self, bag: &Bag) -> Vec<Violation> {
+        self.subsets
+            .iter()
+            .enumerate()
+            .flat_map(|(draw, subset)| {
+                subset.iter().filter_map(move |CubeCount(colour, n)| {
+                    let limit = bag.get(colour).copied().unwrap_or(0);
+                    (*n > limit).then(|| Violation {
+                        draw,
+                        colour: colour.clone(),
+                        count: *n,
+                        limit,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    pub fn is_possible(&self, bag: &Bag) -> bool {
+        self.violations(bag).is_empty()
+    }
+}
+
+/// The games that could have been played with the bag.
+pub fn possible_games<'a>(games: &'a [Game], bag: &Bag) -> Vec<&'a Game> {
+    games.iter().filter(|game| game.is_possible(bag)).collect()
+}
+
+/// The bag of part 1.
+pub fn puzzle_bag() -> Bag {
+    HashMap::from([
+        ("red".to_string(), 12),
+        ("green".to_string(), 13),
+        ("blue".to_string(), 14),
+    ])
+}
+
+fn parse_cube_count(input: &str) -> Result<CubeCount, &'static str> {
+    let (n, colour) = input.trim().split_once(' ').ok_or("Invalid cube count")?;
+
+    Ok(CubeCount(
+        colour.trim().to_string(),
+        n.parse().map_err(|_| "Invalid cube count")?,
+    ))
+}
 
 #[aoc_generator(day2)]
-fn parse_input(input: &str) -> Vec<Game> {
+pub fn parse_input(input: &str) -> Result<Vec<Game>, &'static str> {
     input
         .lines()
         .map(|l| {
-            let (metadata, data) = l.split_once(':').expect("Failed to split on ':'");
+            let (metadata, data) = l.split_once(':').ok_or("Failed to split on ':'")?;
             let id = metadata
                 .split_whitespace()
                 .last()
-                .expect("Invalid metadata")
+                .ok_or("Invalid metadata")?
                 .parse()
-                .expect("Invalid id");
+                .map_err(|_| "Invalid id")?;
             let subsets = data
-                .split(";")
-                .map(|s| {
-                    {
-                        s.split(',').map(|c| match c.trim().split_once(' ') {
-                            Some((n, "red")) => {
-                                CubeCount(Color::RED, n.parse().expect("Invalid cube count"))
-                            }
-                            Some((n, "green")) => {
-                                CubeCount(Color::GREEN, n.parse().expect("Invalid cube count"))
-                            }
-                            Some((n, "blue")) => {
-                                CubeCount(Color::BLUE, n.parse().expect("Invalid cube count"))
-                            }
-                            a => {
-                                panic!("Unknown cubes: {:?}", a)
-                            }
-                        })
-                    }
-                    .collect()
-                })
-                .collect();
+                .split(';')
+                .map(|s| s.split(',').map(parse_cube_count).collect())
+                .collect::<Result<_, _>>()?;
 
-            Game { id, subsets }
+            Ok(Game { id, subsets })
         })
         .collect()
 }
 
-const RED_CUBES: u32 = 12;
-const GREEN_CUBES: u32 = 13;
-const BLUE_CUBES: u32 = 14;
-
 #[aoc(day2, part1)]
 fn solve_part1(input: &[Game]) -> u32 {
-    input
+    possible_games(input, &puzzle_bag())
         .iter()
-        .filter(|game| {
-            game.subsets.iter().flatten().all(|cubes| match cubes {
-                CubeCount(Color::RED, n) => *n <= RED_CUBES,
-                CubeCount(Color::GREEN, n) => *n <= GREEN_CUBES,
-                CubeCount(Color::BLUE, n) => *n <= BLUE_CUBES,
-            })
-        })
         .map(|g| g.id)
         .sum()
 }
 
 #[aoc(day2, part2)]
 fn solve_part2(input: &[Game]) -> u32 {
-    input.iter()
+    let colours = puzzle_bag();
+
+    input
+        .iter()
         .map(|game| {
-            game.subsets.iter().flatten().fold([0, 0, 0], |mut max, cube_count| {
-                match cube_count {
-                    CubeCount(Color::RED, n) => max[0] = cmp::max(max[0], *n),
-                    CubeCount(Color::GREEN, n) => max[1] = cmp::max(max[1], *n),
-                    CubeCount(Color::BLUE, n) => max[2] = cmp::max(max[2], *n),
-                };
-                max
-            }).iter().product::<u32>()
+            let minimum = game.minimum_bag();
+            // a colour that is never shown needs no cubes, which makes the power 0
+            colours
+                .keys()
+                .map(|colour| minimum.get(colour).copied().unwrap_or(0))
+                .product::<u32>()
         })
         .sum()
 }
@@ -102,36 +142,159 @@ Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
 Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
 Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
 
+    fn cubes(colour: &str, n: u32) -> CubeCount {
+        CubeCount(colour.to_string(), n)
+    }
+
+    fn bag(contents: &[(&str, u32)]) -> Bag {
+        contents
+            .iter()
+            .map(|&(colour, n)| (colour.to_string(), n))
+            .collect()
+    }
+
     #[test]
     fn parse_example() {
         assert_eq!(
             parse_input("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green"),
-            vec![Game {
+            Ok(vec![Game {
                 id: 1,
                 subsets: vec![
-                    vec![CubeCount(Color::BLUE, 3), CubeCount(Color::RED, 4)],
-                    vec![
-                        CubeCount(Color::RED, 1),
-                        CubeCount(Color::GREEN, 2),
-                        CubeCount(Color::BLUE, 6)
-                    ],
-                    vec![CubeCount(Color::GREEN, 2)],
+                    vec![cubes("blue", 3), cubes("red", 4)],
+                    vec![cubes("red", 1), cubes("green", 2), cubes("blue", 6)],
+                    vec![cubes("green", 2)],
                 ]
-            }]
+            }])
         )
     }
 
+    #[test]
+    fn parse_any_colour() {
+        assert_eq!(
+            parse_input("Game 7: 2 purple; 1 dark orange, 3 red"),
+            Ok(vec![Game {
+                id: 7,
+                subsets: vec![
+                    vec![cubes("purple", 2)],
+                    vec![cubes("dark orange", 1), cubes("red", 3)],
+                ]
+            }])
+        )
+    }
+
+    #[test]
+    fn parse_invalid_games() {
+        assert_eq!(parse_input("Game 1 3 blue"), Err("Failed to split on ':'"));
+        assert_eq!(parse_input("Game x: 3 blue"), Err("Invalid id"));
+        assert_eq!(parse_input("Game 1: three blue"), Err("Invalid cube count"));
+        assert_eq!(parse_input("Game 1: 3"), Err("Invalid cube count"));
+        assert_eq!(parse_input("Game 1: 3 blue,"), Err("Invalid cube count"));
+    }
+
     #[test]
     fn solve_part1_example() {
-        let input = parse_input(EXAMPLE_INPUT);
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
 
         assert_eq!(solve_part1(&input), 8)
     }
 
     #[test]
     fn solve_part2_example() {
-        let input = parse_input(EXAMPLE_INPUT);
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
 
         assert_eq!(solve_part2(&input), 2286)
     }
+
+    #[test]
+    fn example_minimum_bags() {
+        let games = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(
+            games[0].minimum_bag(),
+            bag(&[("red", 4), ("green", 2), ("blue", 6)])
+        );
+        assert_eq!(
+            games[2].minimum_bag(),
+            bag(&[("red", 20), ("green", 13), ("blue", 6)])
+        );
+        assert!(games
+            .iter()
+            .all(|game| game.is_possible(&game.minimum_bag())));
+    }
+
+    #[test]
+    fn example_violations() {
+        let games = parse_input(EXAMPLE_INPUT).unwrap();
+        let bag = puzzle_bag();
+
+        assert_eq!(games[0].violations(&bag), vec![]);
+        assert_eq!(
+            games[2].violations(&bag),
+            vec![Violation {
+                draw: 0,
+                colour: "red".to_string(),
+                count: 20,
+                limit: 12,
+            }]
+        );
+        assert_eq!(
+            games[3].violations(&bag),
+            vec![
+                Violation {
+                    draw: 2,
+                    colour: "blue".to_string(),
+                    count: 15,
+                    limit: 14,
+                },
+                Violation {
+                    draw: 2,
+                    colour: "red".to_string(),
+                    count: 14,
+                    limit: 12,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn possible_games_for_any_bag() {
+        let games = parse_input(EXAMPLE_INPUT).unwrap();
+        let ids = |bag: &Bag| {
+            possible_games(&games, bag)
+                .iter()
+                .map(|game| game.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(&puzzle_bag()), vec![1, 2, 5]);
+        assert_eq!(
+            ids(&bag(&[("red", 20), ("green", 13), ("blue", 15)])),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            ids(&bag(&[("red", 6), ("green", 3), ("blue", 6)])),
+            vec![1, 2, 5]
+        );
+        assert_eq!(ids(&bag(&[("red", 100), ("green", 100)])), vec![]);
+    }
+
+    #[test]
+    fn colours_missing_from_the_bag() {
+        let games = parse_input("Game 1: 2 purple, 1 red\nGame 2: 3 red").unwrap();
+
+        assert_eq!(
+            games[0].violations(&puzzle_bag()),
+            vec![Violation {
+                draw: 0,
+                colour: "purple".to_string(),
+                count: 2,
+                limit: 0,
+            }]
+        );
+        assert_eq!(
+            possible_games(&games, &bag(&[("purple", 2), ("red", 1)])).len(),
+            1
+        );
+        assert_eq!(solve_part2(&games), 0);
+    }
 }
@@ -1,138 +1,173 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::ops::Range;
 
+/// Column and row of a cell.
 type Coordinate = (usize, usize);
-type Engine = Vec<Vec<char>>;
 
-#[aoc_generator(day3)]
-fn parse_input(input: &str) -> Engine {
-    input.lines().map(|x| x.chars().collect()).collect()
-}
-
-fn is_symbol(c: char) -> bool {
-    !c.is_ascii_digit() && c != '.'
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PartNumber {
+    pub value: u32,
+    pub row: usize,
+    /// Columns taken up by the digits.
+    pub col_span: Range<usize>,
 }
 
-fn is_adjacent(a: Coordinate, b: Coordinate) -> bool {
-    a.0.abs_diff(b.0) <= 1 && a.1.abs_diff(b.1) <= 1
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Symbol {
+    pub char: char,
+    pub pos: Coordinate,
 }
 
-fn get_symbol_coordinates(engine: &Engine) -> Vec<Coordinate> {
-    engine
-        .iter()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.iter()
-                .enumerate()
-                .filter(|(_, &c)| is_symbol(c))
-                .map(move |(x, _)| (x, y))
-        })
-        .collect()
+/// The numbers and symbols of an engine schematic, with which numbers are next to which
+/// symbols.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Schematic {
+    pub numbers: Vec<PartNumber>,
+    pub symbols: Vec<Symbol>,
+    /// Indices of the numbers next to every symbol.
+    numbers_by_symbol: Vec<Vec<usize>>,
+    /// Indices of the symbols next to every number.
+    symbols_by_number: Vec<Vec<usize>>,
 }
 
-fn get_gear_coordinates(engine: &Engine) -> Vec<Coordinate> {
-    engine
-        .iter()
-        .enumerate()
-        .flat_map(|(y, line)| {
-            line.iter()
-                .enumerate()
-                .filter(|(_, &c)| c == '*')
-                .map(move |(x, _)| (x, y))
-        })
-        .collect()
-}
-
-#[aoc(day3, part1)]
-fn solve_part1(engine: &Engine) -> u32 {
-    let symbols = get_symbol_coordinates(engine);
-
-    let mut solution = 0;
+impl From<&str> for Schematic {
+    fn from(input: &str) -> Self {
+        let mut numbers = vec![];
+        let mut symbols = vec![];
 
-    for y in 0..engine.len() {
-        let mut acc = 0;
-        let mut near_symbol = false;
+        for (y, line) in input.lines().enumerate() {
+            let mut number: Option<PartNumber> = None;
 
-        for x in 0..engine[0].len() {
-            match engine[y][x].to_digit(10) {
-                Some(d) => {
-                    acc = 10 * acc + d;
-                    if !near_symbol {
-                        near_symbol = symbols.iter().any(|s| is_adjacent((x, y), *s))
+            for (x, c) in line.chars().enumerate() {
+                match (c.to_digit(10), &mut number) {
+                    (Some(d), Some(part)) => {
+                        part.value = 10 * part.value + d;
+                        part.col_span.end = x + 1;
                     }
-                }
-                None => {
-                    if acc > 0 && near_symbol {
-                        solution += acc;
+                    (Some(d), None) => {
+                        number = Some(PartNumber {
+                            value: d,
+                            row: y,
+                            col_span: x..x + 1,
+                        })
+                    }
+                    (None, _) => {
+                        numbers.extend(number.take());
+                        if is_symbol(c) {
+                            symbols.push(Symbol {
+                                char: c,
+                                pos: (x, y),
+                            });
+                        }
                     }
-                    near_symbol = false;
-                    acc = 0;
                 }
             }
-        }
 
-        if acc > 0 && near_symbol {
-            solution += acc;
+            numbers.extend(number);
         }
-    }
 
-    return solution;
+        Schematic::new(numbers, symbols)
+    }
 }
 
-#[aoc(day3, part2)]
-fn solve_part2(engine: &Engine) -> u32 {
-    let gears = get_gear_coordinates(engine);
-    let mut gears_numbers: Vec<Vec<u32>> = vec![vec![]; gears.len()];
-
-    for y in 0..engine.len() {
-        let mut acc = 0;
-        let mut near_gears = HashSet::new();
-
-        for x in 0..engine[0].len() {
-            match engine[y][x].to_digit(10) {
-                Some(d) => {
-                    acc = 10 * acc + d;
-                    near_gears.extend(
-                        gears
-                            .iter()
-                            .enumerate()
-                            .filter(|(_, s)| is_adjacent((x, y), **s))
-                            .map(|(i, _)| i)
-                    );
-                }
-                None => {
-                    if acc > 0 && !near_gears.is_empty() {
-                        for i in near_gears {
-                            gears_numbers[i].push(acc);
-                        }
+impl Schematic {
+    pub fn new(numbers: Vec<PartNumber>, symbols: Vec<Symbol>) -> Self {
+        let positions: HashMap<Coordinate, usize> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (symbol.pos, i))
+            .collect();
+
+        let mut numbers_by_symbol = vec![vec![]; symbols.len()];
+        let mut symbols_by_number = vec![vec![]; numbers.len()];
+
+        // only the cells around a number can hold a symbol next to it
+        for (i, number) in numbers.iter().enumerate() {
+            let columns = number.col_span.start.saturating_sub(1)..=number.col_span.end;
+            for y in number.row.saturating_sub(1)..=number.row + 1 {
+                for x in columns.clone() {
+                    if let Some(&symbol) = positions.get(&(x, y)) {
+                        numbers_by_symbol[symbol].push(i);
+                        symbols_by_number[i].push(symbol);
                     }
-                    near_gears = HashSet::new();
-                    acc = 0;
                 }
-            };
-
+            }
         }
 
-        if acc > 0 && !near_gears.is_empty() {
-            for i in near_gears {
-                gears_numbers[i].push(acc);
-            }
+        Schematic {
+            numbers,
+            symbols,
+            numbers_by_symbol,
+            symbols_by_number,
         }
     }
 
-    return gears_numbers
-        .iter()
-        .filter(|numbers| numbers.len() == 2)
-        .map(|numbers| numbers[0]*numbers[1])
-        .sum();
+    /// The numbers next to at least one symbol.
+    pub fn part_numbers(&self) -> impl Iterator<Item = &PartNumber> {
+        self.numbers
+            .iter()
+            .zip(&self.symbols_by_number)
+            .filter(|(_, symbols)| !symbols.is_empty())
+            .map(|(number, _)| number)
+    }
+
+    /// The numbers next to the symbol at `index` in `symbols`.
+    pub fn numbers_next_to(&self, index: usize) -> impl Iterator<Item = &PartNumber> {
+        self.numbers_by_symbol[index]
+            .iter()
+            .map(|&i| &self.numbers[i])
+    }
+
+    /// The numbers next to at least one symbol drawn as `c`.
+    pub fn numbers_touching(&self, c: char) -> impl Iterator<Item = &PartNumber> {
+        self.numbers
+            .iter()
+            .zip(&self.symbols_by_number)
+            .filter(move |(_, symbols)| symbols.iter().any(|&i| self.symbols[i].char == c))
+            .map(|(number, _)| number)
+    }
+
+    /// The product of the two numbers next to every `*` that is next to exactly two numbers.
+    pub fn gear_ratios(&self) -> impl Iterator<Item = u32> + '_ {
+        self.symbols
+            .iter()
+            .zip(&self.numbers_by_symbol)
+            .filter(|(symbol, numbers)| symbol.char == '*' && numbers.len() == 2)
+            .map(|(_, numbers)| self.numbers[numbers[0]].value * self.numbers[numbers[1]].value)
+    }
+}
+
+#[aoc_generator(day3)]
+fn parse_input(input: &str) -> Schematic {
+    Schematic::from(input)
+}
+
+fn is_symbol(c: char) -> bool {
+    !c.is_ascii_digit() && c != '.'
+}
+
+#[aoc(day3, part1)]
+fn solve_part1(schematic: &Schematic) -> u32 {
+    schematic.part_numbers().map(|number| number.value).sum()
+}
+
+#[aoc(day3, part2)]
+fn solve_part2(schematic: &Schematic) -> u32 {
+    schematic.gear_ratios().sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::xorshift;
 
     const EXAMPLE_ENGINE: &str = "467..114..\n...*......\n..35..633.\n......#...\n617*......\n.....+.58.\n..592.....\n......755.\n...$.*....\n.664.598..";
 
+    fn values<'a>(numbers: impl Iterator<Item = &'a PartNumber>) -> Vec<u32> {
+        numbers.map(|number| number.value).collect()
+    }
+
     #[test]
     fn solve_example_part_1() {
         assert_eq!(solve_part1(&parse_input(EXAMPLE_ENGINE)), 4361);
@@ -168,4 +203,138 @@ mod tests {
     fn solve_example_part_2() {
         assert_eq!(solve_part2(&parse_input(EXAMPLE_ENGINE)), 467835);
     }
+
+    #[test]
+    fn parse_example() {
+        let schematic = parse_input(EXAMPLE_ENGINE);
+
+        assert_eq!(schematic.numbers.len(), 10);
+        assert_eq!(
+            schematic.numbers[0],
+            PartNumber {
+                value: 467,
+                row: 0,
+                col_span: 0..3,
+            }
+        );
+        assert_eq!(
+            schematic.numbers[9],
+            PartNumber {
+                value: 598,
+                row: 9,
+                col_span: 5..8,
+            }
+        );
+        assert_eq!(
+            schematic.symbols,
+            vec![
+                Symbol {
+                    char: '*',
+                    pos: (3, 1)
+                },
+                Symbol {
+                    char: '#',
+                    pos: (6, 3)
+                },
+                Symbol {
+                    char: '*',
+                    pos: (3, 4)
+                },
+                Symbol {
+                    char: '+',
+                    pos: (5, 5)
+                },
+                Symbol {
+                    char: '$',
+                    pos: (3, 8)
+                },
+                Symbol {
+                    char: '*',
+                    pos: (5, 8)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_at_the_edges() {
+        let schematic = parse_input("12#\n..3\n4..");
+
+        assert_eq!(values(schematic.numbers.iter()), vec![12, 3, 4]);
+        assert_eq!(schematic.numbers[1].col_span, 2..3);
+        assert_eq!(values(schematic.part_numbers()), vec![12, 3]);
+    }
+
+    #[test]
+    fn example_queries() {
+        let schematic = parse_input(EXAMPLE_ENGINE);
+
+        assert_eq!(
+            values(schematic.part_numbers()),
+            vec![467, 35, 633, 617, 592, 755, 664, 598]
+        );
+        assert_eq!(
+            schematic.gear_ratios().collect::<Vec<_>>(),
+            vec![16345, 451490]
+        );
+        assert_eq!(values(schematic.numbers_touching('#')), vec![633]);
+        assert_eq!(values(schematic.numbers_touching('$')), vec![664]);
+        assert_eq!(values(schematic.numbers_touching('+')), vec![592]);
+        assert_eq!(
+            values(schematic.numbers_touching('*')),
+            vec![467, 35, 617, 755, 598]
+        );
+        assert_eq!(values(schematic.numbers_touching('&')), vec![]);
+        assert_eq!(values(schematic.numbers_next_to(2)), vec![617]);
+    }
+
+    #[test]
+    fn number_touching_several_symbols() {
+        let schematic = parse_input("*..\n.5*\n...");
+
+        assert_eq!(values(schematic.part_numbers()), vec![5]);
+        assert_eq!(values(schematic.numbers_touching('*')), vec![5]);
+        assert_eq!(values(schematic.numbers_next_to(0)), vec![5]);
+        assert_eq!(values(schematic.numbers_next_to(1)), vec![5]);
+        assert_eq!(schematic.gear_ratios().count(), 0);
+    }
+
+    #[test]
+    fn adjacency_matches_brute_force() {
+        let mut rng = xorshift(3);
+
+        for _ in 0..200 {
+            let input = (0..8)
+                .map(|_| {
+                    (0..8)
+                        .map(|_| match rng() % 8 {
+                            0 => '*',
+                            1 => '#',
+                            2..=4 => '.',
+                            _ => char::from(b'0' + (rng() % 10) as u8),
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let schematic = parse_input(&input);
+
+            for (i, symbol) in schematic.symbols.iter().enumerate() {
+                let expected: Vec<u32> = schematic
+                    .numbers
+                    .iter()
+                    .filter(|number| {
+                        number.row.abs_diff(symbol.pos.1) <= 1
+                            && number
+                                .col_span
+                                .clone()
+                                .any(|x| x.abs_diff(symbol.pos.0) <= 1)
+                    })
+                    .map(|number| number.value)
+                    .collect();
+
+                assert_eq!(values(schematic.numbers_next_to(i)), expected, "{}", input);
+            }
+        }
+    }
 }
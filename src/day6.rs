@@ -1,48 +1,137 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
+use std::ops::RangeInclusive;
 
-type Race = (u64, u64);
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Race {
+    pub time: u128,
+    pub distance: u128,
+}
 
 #[aoc_generator(day6, part1)]
-fn parse_input_part1(input: &str) -> Vec<Race> {
+pub fn parse_input_part1(input: &str) -> Vec<Race> {
 
     let lines: Vec<&str> = input.lines().collect();
-    let times: Vec<u64> = lines[0].split_whitespace().skip(1).map(|i| i.parse().unwrap()).collect();
-    let distances: Vec<u64> = lines[1].split_whitespace().skip(1).map(|i| i.parse().unwrap()).collect();
+    let times: Vec<u128> = lines[0].split_whitespace().skip(1).map(|i| i.parse().unwrap()).collect();
+    let distances: Vec<u128> = lines[1].split_whitespace().skip(1).map(|i| i.parse().unwrap()).collect();
 
-    times.into_iter().zip(distances).collect()
+    times.into_iter().zip(distances).map(|(time, distance)| Race { time, distance }).collect()
 }
 
 #[aoc_generator(day6, part2)]
-fn parse_input_part2(input: &str) -> Race {
+pub fn parse_input_part2(input: &str) -> Race {
 
     let lines: Vec<&str> = input.lines().collect();
-    let time: u64 = lines[0].split_whitespace().skip(1).join("").parse().unwrap();
-    let distance: u64 = lines[1].split_whitespace().skip(1).join("").parse().unwrap();
+    let time: u128 = lines[0].split_whitespace().skip(1).join("").parse().unwrap();
+    let distance: u128 = lines[1].split_whitespace().skip(1).join("").parse().unwrap();
 
-    (time, distance)
+    Race { time, distance }
 }
 
 #[aoc(day6, part1)]
-fn solve_part1(input: &[Race]) -> u64 {
-    input.iter().map(|&(time, distance)| {
-        ways_to_beat_race(time, distance)
+fn solve_part1(input: &[Race]) -> u128 {
+    input.iter().map(|race| race.ways_to_win()).product()
+}
 
-    }).product()
+#[aoc(day6, part2)]
+fn solve_part2(input: &Race) -> u128 {
+    input.ways_to_win()
 }
 
-fn ways_to_beat_race(time: u64, distance: u64) -> u64 {
-    let x0 = ((time as f64 - ((time as f64).powi(2) - 4.0 * ((distance as f64) + 1.0)).sqrt()) / 2.0).ceil() as u64;
-    let x1 = ((time as f64 + ((time as f64).powi(2) - 4.0 * ((distance as f64) + 1.0)).sqrt()) / 2.0).floor() as u64;
+impl Race {
+    /// Distance travelled when holding the button for `hold` milliseconds, or `None` when it
+    /// does not fit in a `u128`. Holding longer than the race lasts does not move the boat.
+    pub fn distance_for(&self, hold: u128) -> Option<u128> {
+        hold.checked_mul(self.time.saturating_sub(hold))
+    }
+
+    fn beats(&self, hold: u128) -> bool {
+        // a distance that does not fit in a u128 certainly beats the record
+        self.distance_for(hold).is_none_or(|d| d > self.distance)
+    }
+
+    /// The hold time that travels the furthest. For an odd race time, holding one millisecond
+    /// longer travels just as far.
+    pub fn best_hold(&self) -> u128 {
+        self.time / 2
+    }
+
+    pub fn max_distance(&self) -> Option<u128> {
+        self.distance_for(self.best_hold())
+    }
+
+    pub fn can_win(&self) -> bool {
+        self.beats(self.best_hold())
+    }
+
+    /// All hold times that beat the record, `None` when the race cannot be won.
+    ///
+    /// The winning hold times are symmetric around `time / 2`, so only the first one is needed.
+    pub fn winning_interval(&self) -> Option<RangeInclusive<u128>> {
+        let x0 = self.first_win()?;
+        Some(x0..=self.time - x0)
+    }
+
+    pub fn ways_to_win(&self) -> u128 {
+        self.winning_interval()
+            .map_or(0, |interval| interval.end() - interval.start() + 1)
+    }
 
-    x1 - x0 + 1
+    /// The shortest hold time that beats the record, if any.
+    ///
+    /// Estimated as the lower root of `x² - time·x + distance + 1 = 0` with an exact integer
+    /// square root and then corrected by stepping. When `time²` does not fit in a `u128` the
+    /// first win is binary searched instead.
+    fn first_win(&self) -> Option<u128> {
+        let (time, distance) = (self.time, self.distance);
+        if !self.can_win() {
+            return None;
+        }
+
+        let discriminant = time
+            .checked_mul(time)
+            .and_then(|t2| t2.checked_sub(distance.checked_add(1)?.checked_mul(4)?));
+
+        let mut x0 = match discriminant {
+            Some(discriminant) => (time - isqrt(discriminant)).div_ceil(2),
+            None => {
+                let (mut low, mut high) = (0, self.best_hold());
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    match self.beats(mid) {
+                        true => high = mid,
+                        false => low = mid + 1,
+                    }
+                }
+                low
+            }
+        };
+
+        while x0 > 0 && self.beats(x0 - 1) {
+            x0 -= 1;
+        }
+        while !self.beats(x0) {
+            x0 += 1;
+        }
+        Some(x0)
+    }
 }
 
-#[aoc(day6, part2)]
-fn solve_part2(input: &Race) -> u64 {
-    let (time, distance) = *input;
+/// Integer square root: an `f64` estimate refined with a Newton step, then corrected so it is
+/// exact for every `u128`.
+fn isqrt(n: u128) -> u128 {
+    let mut root = (n as f64).sqrt() as u128;
+    if root > 0 {
+        root = (root + n / root) / 2;
+    }
 
-    ways_to_beat_race(time, distance)
+    while root.checked_mul(root).is_none_or(|square| square > n) {
+        root -= 1;
+    }
+    while (root + 1).checked_mul(root + 1).is_some_and(|square| square <= n) {
+        root += 1;
+    }
+    root
 }
 
 #[cfg(test)]
@@ -51,17 +140,25 @@ mod tests {
 
     const EXAMPLE_INPUT: &str = "Time:      7  15   30\nDistance:  9  40  200";
 
+    fn race(time: u128, distance: u128) -> Race {
+        Race { time, distance }
+    }
+
+    fn ways_to_beat_race(time: u128, distance: u128) -> u128 {
+        race(time, distance).ways_to_win()
+    }
+
     #[test]
     fn parse_example_input_part1() {
 
-        assert_eq!(parse_input_part1(EXAMPLE_INPUT), vec![(7, 9), (15, 40), (30, 200)])
+        assert_eq!(parse_input_part1(EXAMPLE_INPUT), vec![race(7, 9), race(15, 40), race(30, 200)])
 
     }
 
     #[test]
     fn parse_example_input_part2() {
 
-        assert_eq!(parse_input_part2(EXAMPLE_INPUT), (71530, 940200))
+        assert_eq!(parse_input_part2(EXAMPLE_INPUT), race(71530, 940200))
 
     }
 
@@ -74,4 +171,117 @@ mod tests {
     fn solve_example_part2() {
         assert_eq!(solve_part2(&parse_input_part2(EXAMPLE_INPUT)), 71503);
     }
+
+    #[test]
+    fn example_winning_intervals() {
+        let intervals = parse_input_part1(EXAMPLE_INPUT)
+            .iter()
+            .map(Race::winning_interval)
+            .collect::<Vec<_>>();
+
+        assert_eq!(intervals, vec![Some(2..=5), Some(4..=11), Some(11..=19)]);
+    }
+
+    #[test]
+    fn example_best_hold() {
+        let best = race(7, 9);
+
+        assert_eq!(best.best_hold(), 3);
+        assert_eq!(best.max_distance(), Some(12));
+        assert_eq!(best.distance_for(4), Some(12));
+        assert_eq!(best.distance_for(7), Some(0));
+        assert_eq!(best.distance_for(8), Some(0));
+        assert_eq!(race(30, 200).max_distance(), Some(225));
+    }
+
+    #[test]
+    fn unwinnable_races() {
+        for unwinnable in [race(0, 0), race(1, 0), race(7, 12), race(8, 16), race(10, 1000)] {
+            assert!(!unwinnable.can_win(), "{:?}", unwinnable);
+            assert_eq!(unwinnable.winning_interval(), None);
+            assert_eq!(unwinnable.ways_to_win(), 0);
+        }
+    }
+
+    #[test]
+    fn barely_winnable_races() {
+        assert_eq!(race(2, 0).winning_interval(), Some(1..=1));
+        assert_eq!(race(7, 11).winning_interval(), Some(3..=4));
+        assert_eq!(race(8, 15).winning_interval(), Some(4..=4));
+        assert_eq!(race(3, 0).winning_interval(), Some(1..=2));
+    }
+
+    #[test]
+    fn huge_races() {
+        assert_eq!(race(u128::MAX, 0).max_distance(), None);
+        assert!(race(u128::MAX, u128::MAX).can_win());
+        assert_eq!(race(u128::MAX, u128::MAX).winning_interval(), Some(2..=u128::MAX - 2));
+    }
+
+    fn brute_force(time: u128, distance: u128) -> u128 {
+        (0..=time).filter(|x| x * (time - x) > distance).count() as u128
+    }
+
+    #[test]
+    fn test_isqrt() {
+        for n in 0..10_000 {
+            let root = isqrt(n);
+            assert!(root * root <= n && (root + 1) * (root + 1) > n, "{}", n);
+        }
+
+        for root in [1u128 << 32, (1 << 53) + 1, (1 << 63) + 3, u64::MAX as u128] {
+            let square = root * root;
+            assert_eq!(isqrt(square), root);
+            assert_eq!(isqrt(square - 1), root - 1);
+            assert_eq!(isqrt(square + 1), root);
+        }
+
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn ways_match_brute_force() {
+        for time in 0..60 {
+            for distance in 0..=time * time / 4 + 2 {
+                assert_eq!(
+                    ways_to_beat_race(time, distance),
+                    brute_force(time, distance),
+                    "time {} distance {}",
+                    time,
+                    distance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ways_with_perfect_square_discriminant() {
+        // roots at exactly 10 and 20, which only tie the record
+        assert_eq!(ways_to_beat_race(30, 200), 9);
+        // roots at exactly 2 and 5
+        assert_eq!(ways_to_beat_race(7, 10), 2);
+        // discriminant of zero, the only best hold time ties the record
+        assert_eq!(ways_to_beat_race(8, 16), 0);
+        assert_eq!(ways_to_beat_race(8, 15), 1);
+    }
+
+    #[test]
+    fn ways_for_large_races() {
+        // with time = 2h and distance = h² - k² - 1, exactly the hold times h - k..=h + k win
+        for (h, k) in [
+            ((1u128 << 40) + 12345, 1000),
+            (1 << 52, 0),
+            (u32::MAX as u128, 77),
+            ((1 << 63) + 7, 5),
+            (1 << 63, 1 << 20),
+        ] {
+            assert_eq!(ways_to_beat_race(2 * h, h * h - k * k - 1), 2 * k + 1);
+            assert_eq!(ways_to_beat_race(2 * h + 1, h * h + h - k * k - k - 1), 2 * k + 2);
+        }
+
+        assert_eq!(ways_to_beat_race(u64::MAX as u128, 0), u64::MAX as u128 - 1);
+        // every hold time but 0, 1, time - 1 and time travels further than u128::MAX
+        assert_eq!(ways_to_beat_race(u128::MAX, u128::MAX), u128::MAX - 3);
+        assert_eq!(ways_to_beat_race(u128::MAX, 1 << 100), u128::MAX - 1);
+    }
 }
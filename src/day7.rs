@@ -1,8 +1,11 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 
-#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone)]
-enum Card {
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Card {
     Ace,
     King,
     Queen,
@@ -16,7 +19,6 @@ enum Card {
     Four,
     Three,
     Two,
-    Joker,
 }
 
 impl TryFrom<char> for Card {
@@ -37,95 +39,406 @@ impl TryFrom<char> for Card {
             '4' => Ok(Card::Four),
             '3' => Ok(Card::Three),
             '2' => Ok(Card::Two),
-            '*' => Ok(Card::Joker),
             _ => Err("Card not recognized"),
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone)]
-enum CardsType {
-    FiveOKind,
-    FourOKind,
-    FullHouse,
-    ThreeOKind,
-    TwoPair,
-    OnePair,
-    HighCard,
+impl Display for Card {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let c = match self {
+            Card::Ace => 'A',
+            Card::King => 'K',
+            Card::Queen => 'Q',
+            Card::Jack => 'J',
+            Card::Ten => 'T',
+            Card::Nine => '9',
+            Card::Eight => '8',
+            Card::Seven => '7',
+            Card::Six => '6',
+            Card::Five => '5',
+            Card::Four => '4',
+            Card::Three => '3',
+            Card::Two => '2',
+        };
+        write!(f, "{}", c)
+    }
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
-struct Hand {
-    cards: Vec<Card>,
-    bid: usize,
+/// Number of cards in a hand.
+const HAND_SIZE: usize = 5;
+
+/// Number of different cards.
+const CARDS: usize = 13;
+
+/// Bits per card rank in a sort key.
+const RANK_BITS: usize = 4;
+
+/// Every way to split `n` cards into groups of equal cards no larger than `max`, largest group
+/// first.
+fn partitions(n: usize, max: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+
+    (1..=n.min(max))
+        .rev()
+        .flat_map(|first| {
+            partitions(n - first, first)
+                .into_iter()
+                .map(move |mut rest| {
+                    rest.insert(0, first);
+                    rest
+                })
+        })
+        .collect()
+}
+
+/// Every group shape the cards can take when each of the jokers becomes any other card.
+fn shapes(groups: &[usize], jokers: usize) -> Vec<Vec<usize>> {
+    let mut shapes = vec![groups.to_vec()];
+    for _ in 0..jokers {
+        shapes = shapes
+            .into_iter()
+            .flat_map(|shape| {
+                // a joker joins one of the groups, or becomes a card not in the hand yet
+                (0..=shape.len()).map(move |i| {
+                    let mut shape = shape.clone();
+                    match shape.get_mut(i) {
+                        Some(group) => *group += 1,
+                        None => shape.push(1),
+                    }
+                    shape.sort_by_key(|&g| Reverse(g));
+                    shape
+                })
+            })
+            .unique()
+            .collect();
+    }
+    shapes
+}
+
+/// Packs group sizes, largest group first, together with a number of jokers into one number.
+fn shape_code(groups: &[usize], jokers: usize) -> u32 {
+    groups
+        .iter()
+        .fold(jokers as u32 + 1, |code, &group| code << 3 | group as u32)
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct HandType {
+    pub name: &'static str,
+    /// Sizes of the groups of equal cards, largest group first.
+    pub groups: Vec<usize>,
+}
+
+impl HandType {
+    pub fn new(name: &'static str, groups: &[usize]) -> Self {
+        HandType {
+            name,
+            groups: groups
+                .iter()
+                .copied()
+                .sorted_by_key(|&g| Reverse(g))
+                .collect(),
+        }
+    }
+}
+
+/// The hand types of the puzzle, strongest first.
+pub fn standard_hand_types() -> Vec<HandType> {
+    vec![
+        HandType::new("Five of a kind", &[5]),
+        HandType::new("Four of a kind", &[4, 1]),
+        HandType::new("Full house", &[3, 2]),
+        HandType::new("Three of a kind", &[3, 1, 1]),
+        HandType::new("Two pair", &[2, 2, 1]),
+        HandType::new("One pair", &[2, 1, 1, 1]),
+        HandType::new("High card", &[1, 1, 1, 1, 1]),
+    ]
+}
+
+/// Why one hand ranks differently from another.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Decider {
+    /// The hands have different types.
+    Type,
+    /// The hands have the same type and their cards first differ at this position.
+    Card(usize),
+    /// The hands have the same type and equally strong cards.
+    Tie,
+}
+
+/// How a hand gets its rank: first by the type its best substitution forms, then by the
+/// strength of its own cards in order.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Explanation<'a> {
+    pub cards: Vec<Card>,
+    /// The cards with every joker replaced by the card it acts as.
+    pub substitution: Vec<Card>,
+    pub hand_type: &'a HandType,
+    /// Strength of every card, compared in order to break ties. Jokers keep their own strength.
+    pub tiebreak: Vec<usize>,
+}
+
+impl Display for Explanation<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} plays as {}: {}, tiebreak {}",
+            self.cards.iter().join(""),
+            self.substitution.iter().join(""),
+            self.hand_type.name,
+            self.tiebreak.iter().join(" ")
+        )
+    }
 }
 
-impl Hand {
-    fn get_type(&self) -> CardsType {
-        let mut counts = self.cards.iter().counts();
+/// How hands are ranked: which cards are wild, which cards beat which, and which hand types
+/// beat which.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Cards from weakest to strongest, used to break ties between hands of the same type.
+    card_order: Vec<Card>,
+    /// Cards that act like whichever card makes the strongest hand type.
+    jokers: Vec<Card>,
+    /// Hand types from strongest to weakest.
+    hand_types: Vec<HandType>,
+    /// Rank of every card, indexed by card.
+    ranks: [u8; CARDS],
+    /// Position of the strongest hand type, by the shape code of the cards that are not jokers.
+    type_indices: HashMap<u32, usize>,
+}
+
+impl Rules {
+    /// Builds rules from the cards from weakest to strongest, the cards that are wild, and the
+    /// hand types from strongest to weakest.
+    pub fn new(
+        card_order: &str,
+        jokers: &str,
+        hand_types: Vec<HandType>,
+    ) -> Result<Self, &'static str> {
+        let card_order: Vec<Card> = card_order.chars().map(Card::try_from).try_collect()?;
+        let jokers: Vec<Card> = jokers.chars().map(Card::try_from).try_collect()?;
+
+        if card_order.len() != CARDS || !card_order.iter().all_unique() {
+            return Err("Card order must list every card once");
+        }
+        if !jokers.iter().all_unique() {
+            return Err("Jokers must be listed once");
+        }
+        if card_order.len() - jokers.len() < HAND_SIZE {
+            return Err("Too many jokers to form every hand type");
+        }
+        if hand_types
+            .iter()
+            .any(|hand_type| hand_type.groups.iter().sum::<usize>() != HAND_SIZE)
+        {
+            return Err("Hand type must describe five cards");
+        }
+        if !partitions(HAND_SIZE, HAND_SIZE).iter().all(|shape| {
+            hand_types
+                .iter()
+                .any(|hand_type| &hand_type.groups == shape)
+        }) {
+            return Err("Hand types must cover every hand");
+        }
+
+        if hand_types.len() > 1 << (32 - HAND_SIZE * RANK_BITS) {
+            return Err("Too many hand types");
+        }
+
+        let mut ranks = [0; CARDS];
+        for (rank, &card) in card_order.iter().enumerate() {
+            ranks[card as usize] = rank as u8;
+        }
+
+        // every hand has one of few shapes, so the strongest hand type of each is worked out once
+        let mut type_indices = HashMap::new();
+        for jokers in 0..=HAND_SIZE {
+            for groups in partitions(HAND_SIZE - jokers, HAND_SIZE) {
+                let shapes = shapes(&groups, jokers);
+                let index = hand_types
+                    .iter()
+                    .position(|hand_type| shapes.contains(&hand_type.groups))
+                    .expect("Hand types cover every hand");
+                type_indices.insert(shape_code(&groups, jokers), index);
+            }
+        }
+
+        Ok(Rules {
+            card_order,
+            jokers,
+            hand_types,
+            ranks,
+            type_indices,
+        })
+    }
+
+    /// The rules of part 1.
+    pub fn standard() -> Self {
+        Rules::new("23456789TJQKA", "", standard_hand_types()).expect("Valid rules")
+    }
+
+    /// The rules of part 2, where jacks are jokers and the weakest card.
+    pub fn with_jokers() -> Self {
+        Rules::new("J23456789TQKA", "J", standard_hand_types()).expect("Valid rules")
+    }
+
+    /// Strength of a single card, higher is stronger.
+    pub fn card_rank(&self, card: Card) -> usize {
+        self.ranks[card as usize] as usize
+    }
+
+    /// A key that sorts five cards from weakest to strongest hand.
+    ///
+    /// The strength of the hand type is stored above bit 20, followed by the rank of every card
+    /// in 4 bits, first card highest. Hands of different types are therefore ordered by type, and
+    /// hands of the same type by the first card that differs.
+    pub fn sort_key(&self, cards: &[Card]) -> u32 {
+        assert_eq!(cards.len(), HAND_SIZE, "A hand has five cards");
+        let strength = (self.hand_types.len() - 1 - self.type_index(cards)) as u32;
+
+        cards.iter().fold(strength, |key, &card| {
+            key << RANK_BITS | self.ranks[card as usize] as u32
+        })
+    }
+
+    /// The strongest hand type five cards can form.
+    pub fn hand_type(&self, cards: &[Card]) -> &HandType {
+        &self.hand_types[self.type_index(cards)]
+    }
+
+    /// The cards with every joker replaced so they form the strongest hand type. When several
+    /// replacements do, jokers become the strongest cards possible.
+    pub fn best_substitution(&self, cards: &[Card]) -> Vec<Card> {
+        let best = self.type_index(cards);
+        let jokers = cards
+            .iter()
+            .positions(|card| self.jokers.contains(card))
+            .collect_vec();
+
+        // jokers only need to act as a card in the hand or as one of the strongest other cards
+        let present = cards
+            .iter()
+            .filter(|card| !self.jokers.contains(card))
+            .unique()
+            .copied()
+            .collect_vec();
+        let absent = self
+            .card_order
+            .iter()
+            .rev()
+            .filter(|card| !self.jokers.contains(card) && !present.contains(card))
+            .take(jokers.len())
+            .copied();
+        let candidates = present
+            .iter()
+            .copied()
+            .chain(absent)
+            .sorted_by_key(|&card| Reverse(self.card_rank(card)))
+            .collect_vec();
+
+        candidates
+            .into_iter()
+            .combinations_with_replacement(jokers.len())
+            .map(|choice| {
+                let mut substitution = cards.to_vec();
+                for (&position, card) in jokers.iter().zip(choice) {
+                    substitution[position] = card;
+                }
+                substitution
+            })
+            .find(|substitution| self.type_index(substitution) == best)
+            .expect("Some substitution forms the strongest hand type")
+    }
+
+    /// The best substitution, hand type and tiebreak that rank the cards.
+    pub fn explain(&self, cards: &[Card]) -> Explanation<'_> {
+        Explanation {
+            cards: cards.to_vec(),
+            substitution: self.best_substitution(cards),
+            hand_type: self.hand_type(cards),
+            tiebreak: cards.iter().map(|&card| self.card_rank(card)).collect(),
+        }
+    }
 
-        let jokers = counts.remove(&Card::Joker).unwrap_or(0);
+    /// What decides the order of two hands.
+    pub fn decider(&self, a: &[Card], b: &[Card]) -> Decider {
+        if self.type_index(a) != self.type_index(b) {
+            return Decider::Type;
+        }
 
-        let occurrences = counts.values().sorted().rev().collect::<Vec<&usize>>();
+        a.iter()
+            .zip(b)
+            .position(|(&x, &y)| self.card_rank(x) != self.card_rank(y))
+            .map_or(Decider::Tie, Decider::Card)
+    }
 
-        match occurrences[..] {
-            [] if jokers == 5 => CardsType::FiveOKind,
-            [x] if x + jokers == 5 => CardsType::FiveOKind,
-            [x, ..] if x + jokers == 4 => CardsType::FourOKind,
-            [x, y] if x + y + jokers == 5 => CardsType::FullHouse,
-            [x, ..] if x + jokers == 3 => CardsType::ThreeOKind,
-            [x, y, ..] if x + y + jokers == 4 => CardsType::TwoPair,
-            [x, ..] if x + jokers == 2 => CardsType::OnePair,
-            [..] => CardsType::HighCard,
+    /// Position of the strongest hand type five cards can form, lower is stronger.
+    fn type_index(&self, cards: &[Card]) -> usize {
+        let mut counts = [0; CARDS];
+        let mut jokers = 0;
+        for card in cards {
+            match self.jokers.contains(card) {
+                true => jokers += 1,
+                false => counts[*card as usize] += 1,
+            }
         }
+
+        counts.sort_unstable_by_key(|&count| Reverse(count));
+        let groups = counts.iter().take_while(|&&count| count > 0).count();
+
+        self.type_indices[&shape_code(&counts[..groups], jokers)]
     }
 }
 
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Hand {
+    pub cards: Vec<Card>,
+    pub bid: usize,
+}
+
 impl TryFrom<&str> for Hand {
     type Error = &'static str;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let (cards, bid) = value.split_once(' ').ok_or("Failed to split")?;
+        let cards: Vec<Card> = cards.chars().map(Card::try_from).try_collect()?;
+        if cards.len() != HAND_SIZE {
+            return Err("A hand has five cards");
+        }
+
         Ok(Hand {
-            cards: cards.chars().map(|c| Card::try_from(c)).try_collect()?,
+            cards,
             bid: bid.parse().map_err(|_| "Failed to parse bid")?,
         })
     }
 }
 
-
-#[aoc_generator(day7, part1)]
-fn parse_input_part1(input: &str) -> Vec<Hand> {
+#[aoc_generator(day7)]
+pub fn parse_input(input: &str) -> Vec<Hand> {
     input
         .lines()
         .map(|line| Hand::try_from(line).expect("A valid hand"))
         .collect()
 }
 
-#[aoc_generator(day7, part2)]
-fn parse_input_part2(input: &str) -> Vec<Hand> {
-    input
-        .lines()
-        .map(|line| Hand::try_from(line.replace('J', "*").as_str()).expect("A valid hand"))
-        .collect()
-}
-
 #[aoc(day7, part1)]
 fn solve_part1(input: &[Hand]) -> usize {
-    solve(input)
+    solve(input, &Rules::standard())
 }
 
 #[aoc(day7, part2)]
 fn solve_part2(input: &[Hand]) -> usize {
-    solve(input)
+    solve(input, &Rules::with_jokers())
 }
 
-fn solve(input: &[Hand]) -> usize {
+/// Total winnings: every bid multiplied by the rank of its hand, weakest hand first.
+pub fn solve(input: &[Hand], rules: &Rules) -> usize {
     input
         .iter()
-        .sorted_by_key(|hand| &hand.cards)
-        .sorted_by_cached_key(|hand| hand.get_type()) // Cache getting types of hands
-        .rev()
+        .sorted_by_cached_key(|hand| rules.sort_key(&hand.cards))
         .enumerate()
         .map(|(i, hand)| (i + 1) * hand.bid)
         .sum()
@@ -135,6 +448,7 @@ fn solve(input: &[Hand]) -> usize {
 mod tests {
     use super::Card::*;
     use super::*;
+    use crate::test_utils::xorshift;
     use std::vec;
 
     const EXAMPLE_INPUT: &str = "32T3K 765
@@ -143,10 +457,27 @@ KK677 28
 KTJJT 220
 QQQJA 483";
 
+    fn hand_type(rules: &Rules, cards: &str) -> &'static str {
+        rules
+            .hand_type(
+                &Hand::try_from(format!("{} 1", cards).as_str())
+                    .unwrap()
+                    .cards,
+            )
+            .name
+    }
+
+    #[test]
+    fn parse_invalid_hands() {
+        assert_eq!(Hand::try_from("32T3 765"), Err("A hand has five cards"));
+        assert_eq!(Hand::try_from("32T3KK 765"), Err("A hand has five cards"));
+        assert_eq!(Hand::try_from("32T3* 765"), Err("Card not recognized"));
+    }
+
     #[test]
     fn parse_example_input() {
         assert_eq!(
-            parse_input_part1(EXAMPLE_INPUT)[0],
+            parse_input(EXAMPLE_INPUT)[0],
             Hand {
                 cards: vec![Three, Two, Ten, Three, King],
                 bid: 765,
@@ -156,69 +487,356 @@ QQQJA 483";
 
     #[test]
     fn solve_example_part1() {
-        assert_eq!(solve_part1(&parse_input_part1(EXAMPLE_INPUT)), 6440)
+        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT)), 6440)
     }
 
     #[test]
     fn solve_example_part2() {
-        assert_eq!(solve_part2(&parse_input_part2(EXAMPLE_INPUT)), 5905)
+        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT)), 5905)
     }
 
     #[test]
     fn test_hand_types() {
+        let rules = Rules::standard();
+
+        assert_eq!(hand_type(&rules, "AAAAA"), "Five of a kind");
+        assert_eq!(hand_type(&rules, "T5555"), "Four of a kind");
+        assert_eq!(hand_type(&rules, "T55T5"), "Full house");
+        assert_eq!(hand_type(&rules, "T55J5"), "Three of a kind");
+        assert_eq!(hand_type(&rules, "KK677"), "Two pair");
+        assert_eq!(hand_type(&rules, "32T3K"), "One pair");
+        assert_eq!(hand_type(&rules, "32T4K"), "High card");
+    }
+
+    #[test]
+    fn test_hand_types_part2() {
+        let rules = Rules::with_jokers();
+
+        assert_eq!(hand_type(&rules, "JAAAA"), "Five of a kind");
+        assert_eq!(hand_type(&rules, "JJJJJ"), "Five of a kind");
+        assert_eq!(hand_type(&rules, "T5JJJ"), "Four of a kind");
+        assert_eq!(hand_type(&rules, "TJ5T5"), "Full house");
+        assert_eq!(hand_type(&rules, "TJ5Q5"), "Three of a kind");
+        assert_eq!(hand_type(&rules, "K66J7"), "Three of a kind");
+        assert_eq!(hand_type(&rules, "32TJK"), "One pair");
+        assert_eq!(hand_type(&rules, "32T4J"), "One pair");
+    }
+
+    #[test]
+    fn two_wildcards() {
+        let rules = Rules::new("2J3456789TQKA", "2J", standard_hand_types()).unwrap();
+
+        assert_eq!(hand_type(&rules, "2JAAK"), "Four of a kind");
+        assert_eq!(hand_type(&rules, "22JAK"), "Four of a kind");
+        assert_eq!(hand_type(&rules, "2J345"), "Three of a kind");
         assert_eq!(
-            Hand::try_from("AAAAA 1").unwrap().get_type(),
-            CardsType::FiveOKind
+            solve(&parse_input("2AAAK 1\nJAAK3 2\nAAKK3 3"), &rules),
+            3 + 2 * 2 + 3
         );
+    }
+
+    #[test]
+    fn ace_low() {
+        let rules = Rules::new("A23456789TJQK", "", standard_hand_types()).unwrap();
+
+        assert!(rules.card_rank(Ace) < rules.card_rank(Two));
         assert_eq!(
-            Hand::try_from("T5555 1").unwrap().get_type(),
-            CardsType::FourOKind
+            solve(&parse_input("A2345 1\n2345K 2\nKKAAQ 3"), &rules),
+            1 + 2 * 2 + 3 * 3
         );
+    }
+
+    #[test]
+    fn flush_counts() {
+        // five different cards form a flush, which beats two pair
+        let mut hand_types = standard_hand_types();
+        let high_card = hand_types.pop().unwrap();
+        hand_types.insert(4, HandType::new("Flush", &high_card.groups));
+        let rules = Rules::new("J23456789TQKA", "J", hand_types).unwrap();
+
+        assert_eq!(hand_type(&rules, "32T4K"), "Flush");
+        assert_eq!(hand_type(&rules, "32T4J"), "Flush");
+        assert_eq!(rules.best_substitution(&cards("32T4J")), cards("32T4A"));
+        assert_eq!(hand_type(&rules, "32T3K"), "One pair");
+        assert_eq!(
+            solve(&parse_input("KK677 1\n32T4K 2\n32T3K 3"), &rules),
+            3 + 2 + 3 * 2
+        );
+    }
+
+    fn cards(cards: &str) -> Vec<Card> {
+        cards.chars().map(|c| Card::try_from(c).unwrap()).collect()
+    }
+
+    fn substitution(rules: &Rules, hand: &str) -> String {
+        rules.best_substitution(&cards(hand)).iter().join("")
+    }
+
+    #[test]
+    fn example_substitutions() {
+        let rules = Rules::with_jokers();
+
+        assert_eq!(substitution(&rules, "32T3K"), "32T3K");
+        assert_eq!(substitution(&rules, "T55J5"), "T5555");
+        assert_eq!(substitution(&rules, "KTJJT"), "KTTTT");
+        assert_eq!(substitution(&rules, "QQQJA"), "QQQQA");
+        assert_eq!(substitution(&Rules::standard(), "QQQJA"), "QQQJA");
+    }
+
+    #[test]
+    fn substitutions_with_many_jokers() {
+        let rules = Rules::with_jokers();
+
+        assert_eq!(substitution(&rules, "JJJJJ"), "AAAAA");
+        assert_eq!(substitution(&rules, "JJJJ2"), "22222");
+        assert_eq!(substitution(&rules, "J2J3J"), "32333");
+        assert_eq!(substitution(&rules, "JJ2KJ"), "KK2KK");
+        assert_eq!(substitution(&rules, "32T4J"), "32T4T");
+
+        let two_wildcards = Rules::new("2J3456789TQKA", "2J", standard_hand_types()).unwrap();
+        assert_eq!(substitution(&two_wildcards, "2J345"), "55345");
+        assert_eq!(substitution(&two_wildcards, "22JJ2"), "AAAAA");
+    }
+
+    #[test]
+    fn substitutions_match_hand_types() {
+        let rules = Rules::with_jokers();
+
+        for hand in [
+            "JJJJJ", "J2J3J", "JJ2KJ", "T55J5", "KTJJT", "2345J", "KK677", "AJAJ3",
+        ] {
+            let substitution = rules.best_substitution(&cards(hand));
+
+            assert!(substitution.iter().all(|&card| card != Jack), "{}", hand);
+            assert_eq!(
+                Rules::standard().hand_type(&substitution),
+                rules.hand_type(&cards(hand)),
+                "{}",
+                hand
+            );
+        }
+    }
+
+    #[test]
+    fn explain_hand() {
+        let rules = Rules::with_jokers();
+        let explanation = rules.explain(&cards("KTJJT"));
+
+        assert_eq!(explanation.substitution, cards("KTTTT"));
+        assert_eq!(explanation.hand_type.name, "Four of a kind");
+        assert_eq!(explanation.tiebreak, vec![11, 9, 0, 0, 9]);
         assert_eq!(
-            Hand::try_from("T55T5 1").unwrap().get_type(),
-            CardsType::FullHouse
+            explanation.to_string(),
+            "KTJJT plays as KTTTT: Four of a kind, tiebreak 11 9 0 0 9"
         );
+    }
+
+    #[test]
+    fn example_deciders() {
+        let standard = Rules::standard();
+        let jokers = Rules::with_jokers();
+
         assert_eq!(
-            Hand::try_from("T55J5 1").unwrap().get_type(),
-            CardsType::ThreeOKind
+            standard.decider(&cards("KK677"), &cards("KTJJT")),
+            Decider::Card(1)
         );
-        assert_eq!(Hand::try_from("KK677 1").unwrap().get_type(), CardsType::TwoPair);
-        assert_eq!(Hand::try_from("32T3K 1").unwrap().get_type(), CardsType::OnePair);
         assert_eq!(
-            Hand::try_from("32T4K 1").unwrap().get_type(),
-            CardsType::HighCard
+            jokers.decider(&cards("KK677"), &cards("KTJJT")),
+            Decider::Type
+        );
+        assert_eq!(
+            standard.decider(&cards("T55J5"), &cards("QQQJA")),
+            Decider::Card(0)
+        );
+        assert_eq!(
+            jokers.decider(&cards("T55J5"), &cards("QQQJA")),
+            Decider::Card(0)
+        );
+        assert_eq!(
+            jokers.decider(&cards("KTJJT"), &cards("KTJJT")),
+            Decider::Tie
+        );
+        assert_eq!(
+            jokers.decider(&cards("JKKK2"), &cards("QQQQ2")),
+            Decider::Card(0)
+        );
+        assert_eq!(
+            standard.decider(&cards("JKKK2"), &cards("QQQQ2")),
+            Decider::Type
         );
     }
 
+    fn random_hands(seed: u64, count: usize) -> Vec<Hand> {
+        let all = cards("AKQJT98765432");
+        let mut rng = xorshift(seed);
+
+        (0..count)
+            .map(|bid| Hand {
+                // few different cards so that every hand type shows up
+                cards: (0..5)
+                    .map(|_| all[(rng() % (3 + bid as u64 % 11)) as usize])
+                    .collect(),
+                bid,
+            })
+            .collect()
+    }
+
     #[test]
-    fn test_hand_types_part2() {
-        assert_eq!(
-            Hand::try_from("*AAAA 1").unwrap().get_type(),
-            CardsType::FiveOKind
+    fn sort_key_layout() {
+        let standard = Rules::standard();
+
+        assert_eq!(standard.sort_key(&cards("23456")), 0x001234);
+        assert_eq!(standard.sort_key(&cards("32T3K")), 0x11081B);
+        assert_eq!(standard.sort_key(&cards("AAAAA")), 0x6CCCCC);
+        assert_eq!(Rules::with_jokers().sort_key(&cards("JJJJJ")), 0x600000);
+        assert_eq!(Rules::with_jokers().sort_key(&cards("KTJJT")), 0x5B9009);
+    }
+
+    #[test]
+    fn sort_key_tie_breaking() {
+        // weakest first: types decide before cards, and the first differing card decides
+        let standard = [
+            "23456", "23457", "2345A", "32456", "AKQJ9", "22345", "2AA34", "33245", "AAKQJ",
+            "22334", "AA223", "22234", "AAA23", "22233", "22223", "AAAAK", "22222", "AAAAA",
+        ];
+        for (a, b) in standard.iter().tuple_windows() {
+            let rules = Rules::standard();
+            assert!(
+                rules.sort_key(&cards(a)) < rules.sort_key(&cards(b)),
+                "{} {}",
+                a,
+                b
+            );
+        }
+
+        let jokers = [
+            "23456", "AKQT9", "J2345", "22345", "2345J", "JJ234", "22234", "JKKK2", "QQQQ2",
+            "JJJJJ", "JAAAA", "22222", "AAAAA",
+        ];
+        for (a, b) in jokers.iter().tuple_windows() {
+            let rules = Rules::with_jokers();
+            assert!(
+                rules.sort_key(&cards(a)) < rules.sort_key(&cards(b)),
+                "{} {}",
+                a,
+                b
+            );
+        }
+    }
+
+    /// Ranks a hand straight from the definitions of the rules: the first hand type any
+    /// substitution of the jokers gives, then the cards in order.
+    fn reference_key(rules: &Rules, hand: &Hand) -> (Reverse<usize>, Vec<usize>) {
+        let shapes = shapes(
+            &hand
+                .cards
+                .iter()
+                .filter(|card| !rules.jokers.contains(card))
+                .counts()
+                .into_values()
+                .sorted_by_key(|&g| Reverse(g))
+                .collect_vec(),
+            hand.cards
+                .iter()
+                .filter(|card| rules.jokers.contains(card))
+                .count(),
         );
+        let index = rules
+            .hand_types
+            .iter()
+            .position(|hand_type| shapes.contains(&hand_type.groups))
+            .unwrap();
+        let ranks = hand
+            .cards
+            .iter()
+            .map(|card| rules.card_order.iter().position(|c| c == card).unwrap())
+            .collect_vec();
+        (Reverse(index), ranks)
+    }
+
+    #[test]
+    fn sort_key_matches_type_then_cards() {
+        let mut flush_types = standard_hand_types();
+        let high_card = flush_types.pop().unwrap();
+        flush_types.insert(4, HandType::new("Flush", &high_card.groups));
+
+        let variants = [
+            Rules::standard(),
+            Rules::with_jokers(),
+            Rules::new("2J3456789TQKA", "2J", standard_hand_types()).unwrap(),
+            Rules::new("A23456789TJQK", "", standard_hand_types()).unwrap(),
+            Rules::new("J23456789TQKA", "J", flush_types).unwrap(),
+        ];
+
+        let hands = random_hands(44, 2000);
+        for rules in &variants {
+            for (a, b) in hands.iter().tuple_windows() {
+                assert_eq!(
+                    rules.sort_key(&a.cards).cmp(&rules.sort_key(&b.cards)),
+                    reference_key(rules, a).cmp(&reference_key(rules, b)),
+                    "{:?} {:?}",
+                    a.cards,
+                    b.cards
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn solve_random_hands() {
+        let hands = random_hands(7, 20_000);
+        let rules = Rules::with_jokers();
 
+        // equal hands keep their input order, which is the order of their bids
+        let expected = hands
+            .iter()
+            .map(|hand| (reference_key(&rules, hand), hand.bid))
+            .sorted()
+            .enumerate()
+            .map(|(i, (_, bid))| (i + 1) * bid)
+            .sum::<usize>();
+
+        assert_eq!(solve(&hands, &rules), expected);
+    }
+
+    /// Times the ranking of a million hands. Run with
+    /// `cargo test --release day7::tests::bench_million_hands -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_million_hands() {
+        let hands = random_hands(7, 1_000_000);
+        let rules = Rules::with_jokers();
+
+        let start = std::time::Instant::now();
+        std::hint::black_box(solve(&hands, &rules));
+        println!("1000000 hands: {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn invalid_rules() {
         assert_eq!(
-            Hand::try_from("***** 1").unwrap().get_type(),
-            CardsType::FiveOKind
+            Rules::new("23456789TJQK", "", standard_hand_types()).unwrap_err(),
+            "Card order must list every card once"
+        );
+        assert_eq!(
+            Rules::new("23456789TJQK*", "", standard_hand_types()).unwrap_err(),
+            "Card not recognized"
         );
-
         assert_eq!(
-            Hand::try_from("T5*** 1").unwrap().get_type(),
-            CardsType::FourOKind
+            Rules::new("23456789TJQKA", "JJ", standard_hand_types()).unwrap_err(),
+            "Jokers must be listed once"
         );
         assert_eq!(
-            Hand::try_from("T*5T5 1").unwrap().get_type(),
-            CardsType::FullHouse
+            Rules::new("23456789TJQKA", "23456789T", standard_hand_types()).unwrap_err(),
+            "Too many jokers to form every hand type"
         );
         assert_eq!(
-            Hand::try_from("T*5J5 1").unwrap().get_type(),
-            CardsType::ThreeOKind
+            Rules::new("23456789TJQKA", "", vec![HandType::new("Six", &[6])]).unwrap_err(),
+            "Hand type must describe five cards"
         );
         assert_eq!(
-            Hand::try_from("K66*7 1").unwrap().get_type(),
-            CardsType::ThreeOKind
+            Rules::new("23456789TJQKA", "", standard_hand_types()[1..].to_vec()).unwrap_err(),
+            "Hand types must cover every hand"
         );
-        assert_eq!(Hand::try_from("32T*K 1").unwrap().get_type(), CardsType::OnePair);
-        assert_eq!(Hand::try_from("32T4* 1").unwrap().get_type(), CardsType::OnePair);
     }
 }
@@ -1,123 +1,411 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
-use std::collections::HashMap;
-use std::ops::ControlFlow;
+use nom::branch::alt;
+use nom::bytes::complete::take_while1;
+use nom::character::complete;
+use nom::character::complete::{line_ending, space0};
+use nom::combinator::all_consuming;
+use nom::multi::{many1, separated_list1};
+use nom::sequence::{delimited, pair, separated_pair};
+use nom::IResult;
+use nom::Parser;
+use std::collections::{BTreeSet, HashMap};
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-enum Instruction {
+use crate::math::{crt, gcd};
+
+const STEPS_OVERFLOW: &str = "Steps do not fit in a u128";
+
+pub type Network = (Vec<Instruction>, HashMap<String, [String; 2]>);
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Instruction {
     Left = 0,
     Right = 1,
 }
 
+fn instruction(input: &str) -> IResult<&str, Instruction> {
+    alt((
+        complete::char('L').map(|_| Instruction::Left),
+        complete::char('R').map(|_| Instruction::Right),
+    ))(input)
+}
+
+/// Node names can be any non-empty run of letters, digits or underscores, in any script.
+fn node_name(input: &str) -> IResult<&str, String> {
+    take_while1(|c: char| c.is_alphanumeric() || c == '_')
+        .map(String::from)
+        .parse(input)
+}
+
+fn node(input: &str) -> IResult<&str, (String, [String; 2])> {
+    let (input, name) = node_name(input)?;
+    let (input, _) = delimited(space0, complete::char('='), space0)(input)?;
+    let (input, (left, right)) = delimited(
+        complete::char('('),
+        separated_pair(node_name, pair(complete::char(','), space0), node_name),
+        complete::char(')'),
+    )(input)?;
+
+    Ok((input, (name, [left, right])))
+}
+
+fn network(input: &str) -> IResult<&str, Network> {
+    separated_pair(
+        many1(instruction),
+        pair(line_ending, line_ending),
+        separated_list1(line_ending, node).map(HashMap::from_iter),
+    )(input)
+}
+
+/// Parses the network, rejecting one whose nodes lead to a node that is not defined.
 #[aoc_generator(day8)]
-fn parse_input(input: &str) -> (Vec<Instruction>, HashMap<String, [String; 2]>) {
-    let (instructions, map) = input
-        .split_once("\n\n")
-        .expect("Failed to split instructions and map");
-
-    (
-        instructions
-            .chars()
-            .map(|i| match i {
-                'L' => Instruction::Left,
-                'R' => Instruction::Right,
-                _ => panic!("Invalid instruction"),
-            })
-            .collect(),
-        HashMap::from_iter(map.lines().map(|line| {
-            (
-                line[0..3].to_string(),
-                [line[7..10].to_string(), line[12..15].to_string()],
-            )
-        })),
-    )
+pub fn parse_input(input: &str) -> Result<Network, &'static str> {
+    let (_, (instructions, map)) =
+        all_consuming(network)(input.trim_end()).map_err(|_| "Invalid network")?;
+
+    match map.values().flatten().all(|next| map.contains_key(next)) {
+        true => Ok((instructions, map)),
+        false => Err("Network leads to an undefined node"),
+    }
 }
 
-#[aoc(day8, part1)]
-fn solve_part1(input: &(Vec<Instruction>, HashMap<String, [String; 2]>)) -> u32 {
-    let (instructions, map) = input;
+/// The network compiled to integer node ids, with a jump table over whole instruction cycles.
+struct Graph<'a> {
+    instructions: &'a [Instruction],
+    /// Node names by id, in sorted order.
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, u32>,
+    /// The left and right neighbours of every node.
+    edges: Vec<[u32; 2]>,
+    /// `jumps[node]` is where a walk from `node` lands after following every instruction once.
+    jumps: Vec<u32>,
+}
 
-    const STARTING_NODE: &str = "AAA";
-    const ENDING_NODE: &str = "ZZZ";
+impl<'a> Graph<'a> {
+    fn new(input: &'a Network) -> Self {
+        let (instructions, map) = input;
 
-    let cf =
-        instructions
+        let names = map.keys().map(String::as_str).sorted().collect_vec();
+        let ids: HashMap<&str, u32> = names
             .iter()
-            .cycle()
-            .try_fold(
-                (STARTING_NODE, 0),
-                |(node, steps), &instruction| match node {
-                    ENDING_NODE => ControlFlow::Break(steps),
-                    _ => ControlFlow::Continue((
-                        map.get(node).unwrap()[instruction as usize].as_str(),
-                        steps + 1,
-                    )),
-                },
-            );
+            .enumerate()
+            .map(|(id, &name)| (name, id as u32))
+            .collect();
+        let edges = names
+            .iter()
+            .map(|&name| map[name].each_ref().map(|next| ids[next.as_str()]))
+            .collect_vec();
+        let jumps = (0..names.len() as u32)
+            .map(|node| {
+                instructions.iter().fold(node, |node, &instruction| {
+                    edges[node as usize][instruction as usize]
+                })
+            })
+            .collect();
+
+        Graph {
+            instructions,
+            names,
+            ids,
+            edges,
+            jumps,
+        }
+    }
+
+    fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    fn step(&self, node: u32, instruction: Instruction) -> u32 {
+        self.edges[node as usize][instruction as usize]
+    }
+
+    /// Whether every node, by id, is an end node.
+    fn ends(&self, is_end: impl Fn(&str) -> bool) -> Vec<bool> {
+        self.names.iter().map(|name| is_end(name)).collect()
+    }
+
+    /// For every node, the first step within one instruction cycle at which a walk from that
+    /// node is on an end node.
+    fn first_ends(&self, ends: &[bool]) -> Vec<Option<usize>> {
+        (0..self.names.len() as u32)
+            .map(|mut node| {
+                for (step, &instruction) in self.instructions.iter().enumerate() {
+                    if ends[node as usize] {
+                        return Some(step);
+                    }
+                    node = self.step(node, instruction);
+                }
+                None
+            })
+            .collect()
+    }
 
-    match cf {
-        ControlFlow::Continue(_) => panic!("Failed to reach end"),
-        ControlFlow::Break(res) => res,
+    /// Walks from `start` until the ghost is back on a node at the same point in the
+    /// instructions.
+    ///
+    /// The cycle is found by jumping whole instruction cycles. The walk can already start to
+    /// repeat partway through the instruction cycle before that, so the exact tail is found by
+    /// walking that last instruction cycle next to its repetition.
+    fn ghost_cycle(&self, start: u32, ends: &[bool]) -> GhostCycle {
+        let len = self.instructions.len();
+
+        let mut seen = vec![None; self.names.len()];
+        let mut boundaries = vec![];
+        let mut node = start;
+        let (first, repeat) = loop {
+            if let Some(first) = seen[node as usize] {
+                break (first, boundaries.len());
+            }
+            seen[node as usize] = Some(boundaries.len());
+            boundaries.push(node);
+            node = self.jumps[node as usize];
+        };
+
+        let cycle = (repeat - first) * len;
+        let tail = match first {
+            0 => 0,
+            _ => {
+                let (mut a, mut b) = (boundaries[first - 1], boundaries[repeat - 1]);
+                let offset = self.instructions.iter().position(|&instruction| {
+                    let same = a == b;
+                    a = self.step(a, instruction);
+                    b = self.step(b, instruction);
+                    same
+                });
+                (first - 1) * len + offset.unwrap_or(len)
+            }
+        };
+
+        let mut end_steps = vec![];
+        let mut node = start;
+        for step in 0..tail + cycle {
+            if ends[node as usize] {
+                end_steps.push(step);
+            }
+            node = self.step(node, self.instructions[step % len]);
+        }
+
+        GhostCycle {
+            tail,
+            cycle,
+            end_steps,
+        }
     }
 }
 
+#[aoc(day8, part1)]
+fn solve_part1(input: &Network) -> Option<usize> {
+    steps_to_end(input, "AAA", |node| node == "ZZZ")
+}
+
+/// Counts the steps needed to walk from `start` to the first node matching `is_end`, skipping
+/// whole instruction cycles in which no end node is visited. `None` when `start` is not a node
+/// or no end node is ever reached.
+pub fn steps_to_end(input: &Network, start: &str, is_end: impl Fn(&str) -> bool) -> Option<usize> {
+    let graph = Graph::new(input);
+    let first_ends = graph.first_ends(&graph.ends(is_end));
+
+    let mut node = graph.id(start)?;
+    // after more instruction cycles than there are nodes the walk is repeating itself
+    for cycles in 0..=graph.names.len() {
+        if let Some(step) = first_ends[node as usize] {
+            return Some(cycles * graph.instructions.len() + step);
+        }
+        node = graph.jumps[node as usize];
+    }
+
+    None
+}
+
 #[aoc(day8, part2)]
-fn solve_part2(input: &(Vec<Instruction>, HashMap<String, [String; 2]>)) -> usize {
-    let (instructions, map) = input;
+fn solve_part2(input: &Network) -> Result<u128, &'static str> {
+    ghost_steps(
+        input,
+        |node| node.ends_with('A'),
+        |node| node.ends_with('Z'),
+    )
+}
 
-    let starting_nodes = map
-        .keys()
-        .filter(|node| node.ends_with("A"))
-        .map(|node| node.as_str())
-        .collect_vec();
+/// Counts the steps until all ghosts, starting at every node matching `is_start`, are on a node
+/// matching `is_end` at the same time.
+pub fn ghost_steps(
+    input: &Network,
+    is_start: impl Fn(&str) -> bool,
+    is_end: impl Fn(&str) -> bool,
+) -> Result<u128, &'static str> {
+    let graph = Graph::new(input);
+    let ends = graph.ends(is_end);
 
-    let results = starting_nodes
+    let cycles = graph
+        .names
         .iter()
-        .map(|node| {
-            let mut current_node = *node;
-
-            instructions
-                .iter()
-                .cycle()
-                .enumerate()
-                .find_map(|(index, instruction)| {
-                    let options = map
-                        .get(current_node)
-                        .expect("always exists at a valid node");
-                    let next_node = match instruction {
-                        Instruction::Left => options[0].as_str(),
-                        Instruction::Right => options[1].as_str(),
-                    };
-
-                    if next_node.ends_with("Z") {
-                        Some(index + 1)
-                    } else {
-                        current_node = next_node;
-                        None
-                    }
-                })
-                .expect("Should find cycle")
-        })
+        .filter(|node| is_start(node))
+        .map(|node| graph.ghost_cycle(graph.ids[node], &ends))
         .collect_vec();
 
-    lcm(&results)
+    synchronise(&cycles)?.ok_or("Ghosts never meet on end nodes")
+}
+
+/// The walk of a single ghost. After `tail` steps the ghost enters a cycle of `cycle` steps, in
+/// which it keeps repeating the same nodes at the same instructions.
+#[derive(Debug, PartialEq)]
+struct GhostCycle {
+    tail: usize,
+    cycle: usize,
+    /// Every step, before `tail + cycle`, at which the ghost is on an end node.
+    end_steps: Vec<usize>,
 }
 
-fn lcm(nums: &[usize]) -> usize {
-    if nums.len() == 1 {
-        return nums[0];
+impl GhostCycle {
+    fn is_end_at(&self, step: usize) -> bool {
+        let step = match step < self.tail {
+            true => step,
+            false => self.tail + (step - self.tail) % self.cycle,
+        };
+        self.end_steps.contains(&step)
     }
+}
+
+const GHOST_COLOURS: [&str; 6] = ["red", "blue", "darkgreen", "orange", "purple", "brown"];
+
+/// Renders the network as a Graphviz DOT document.
+///
+/// Every node gets a labelled edge for both its left and right neighbour. Start nodes are filled
+/// green and end nodes red. With `colour_cycles` the edges that each ghost keeps repeating are
+/// drawn in a colour per ghost.
+pub fn to_dot(
+    input: &Network,
+    is_start: impl Fn(&str) -> bool,
+    is_end: impl Fn(&str) -> bool,
+    colour_cycles: bool,
+) -> String {
+    let graph = Graph::new(input);
+    let ends = graph.ends(is_end);
+    let starts = (0..graph.names.len() as u32)
+        .filter(|&node| is_start(graph.names[node as usize]))
+        .collect_vec();
+
+    // colours of the edges, as (node, instruction), that are part of a ghost's cycle
+    let mut colours: HashMap<(u32, Instruction), Vec<&str>> = HashMap::new();
+    if colour_cycles {
+        for (ghost, &start) in starts.iter().enumerate() {
+            let cycle = graph.ghost_cycle(start, &ends);
+            let colour = GHOST_COLOURS[ghost % GHOST_COLOURS.len()];
 
-    let a = nums[0];
-    let b = lcm(&nums[1..]);
-    a * b / gcd(a, b)
+            let mut node = start;
+            for step in 0..cycle.tail + cycle.cycle {
+                let instruction = graph.instructions[step % graph.instructions.len()];
+                if step >= cycle.tail {
+                    let edge = colours.entry((node, instruction)).or_default();
+                    if !edge.contains(&colour) {
+                        edge.push(colour);
+                    }
+                }
+                node = graph.step(node, instruction);
+            }
+        }
+    }
+
+    let mut dot = String::from("digraph network {\n");
+
+    for (node, name) in graph.names.iter().enumerate() {
+        let fill = match (starts.contains(&(node as u32)), ends[node]) {
+            (true, true) => "palegreen:lightcoral",
+            (true, false) => "palegreen",
+            (false, true) => "lightcoral",
+            (false, false) => continue,
+        };
+        dot.push_str(&format!(
+            "    \"{}\" [style=filled, fillcolor=\"{}\"];\n",
+            name, fill
+        ));
+    }
+
+    for (node, name) in graph.names.iter().enumerate() {
+        for (instruction, label) in [(Instruction::Left, "L"), (Instruction::Right, "R")] {
+            let next = graph.names[graph.step(node as u32, instruction) as usize];
+            let colour = match colours.get(&(node as u32, instruction)) {
+                Some(colours) => format!(", color=\"{}\"", colours.join(":")),
+                None => String::new(),
+            };
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+                name, next, label, colour
+            ));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
 }
 
-fn gcd(a: usize, b: usize) -> usize {
-    if b == 0 {
-        return a;
+/// Finds the first step at which every ghost is on an end node, `Ok(None)` when they never are
+/// and [`STEPS_OVERFLOW`] when the merged cycles of some of the ghosts do not fit in a `u128`.
+///
+/// Before all ghosts are in their cycles the steps are checked one by one. After that a ghost
+/// is on an end node exactly when the step is congruent to one of its end steps in the cycle.
+/// Those congruences are merged ghost by ghost with the Chinese remainder theorem, dropping
+/// every merge that contradicts, so only the partial solutions that survive are carried on.
+fn synchronise(cycles: &[GhostCycle]) -> Result<Option<u128>, &'static str> {
+    let Some(tail) = cycles.iter().map(|cycle| cycle.tail).max() else {
+        return Ok(None);
+    };
+
+    if let Some(step) = (0..tail).find(|&step| cycles.iter().all(|c| c.is_end_at(step))) {
+        return Ok(Some(step as u128));
+    }
+
+    let mut overflows = false;
+    let mut partials = BTreeSet::from([(0, 1)]);
+    for c in cycles {
+        let congruences: BTreeSet<(u128, u128)> = c
+            .end_steps
+            .iter()
+            .filter(|&&step| step >= c.tail)
+            .map(|&step| ((step % c.cycle) as u128, c.cycle as u128))
+            .collect();
+
+        partials = partials
+            .iter()
+            .cartesian_product(&congruences)
+            .filter(|&(&a, &b)| is_consistent(a, b))
+            .filter_map(|(&a, &b)| {
+                // consistent congruences only fail to merge when the modulus overflows
+                let merged = crt(&[a, b]);
+                overflows |= merged.is_none();
+                merged
+            })
+            .collect();
+    }
+
+    let tail = tail as u128;
+    let first = partials
+        .into_iter()
+        .filter_map(|(remainder, modulus)| {
+            let step = match remainder < tail {
+                true => (tail - remainder)
+                    .div_ceil(modulus)
+                    .checked_mul(modulus)
+                    .and_then(|steps| steps.checked_add(remainder)),
+                false => Some(remainder),
+            };
+            overflows |= step.is_none();
+            step
+        })
+        .min();
+
+    match (first, overflows) {
+        (None, true) => Err(STEPS_OVERFLOW),
+        (first, _) => Ok(first),
     }
-    gcd(b, a % b)
+}
+
+/// Whether two congruences have a common solution, which is when they agree modulo the gcd of
+/// their moduli.
+fn is_consistent(a: (u128, u128), b: (u128, u128)) -> bool {
+    let divisor = gcd(a.1, b.1);
+    a.0 % divisor == b.0 % divisor
 }
 
 #[cfg(test)]
@@ -144,7 +432,7 @@ XXX = (XXX, XXX)";
     #[test]
     fn example_input() {
         assert_eq!(
-            parse_input(EXAMPLE_INPUT),
+            parse_input(EXAMPLE_INPUT).unwrap(),
             (
                 vec![Instruction::Left, Instruction::Left, Instruction::Right],
                 HashMap::from([
@@ -158,18 +446,322 @@ XXX = (XXX, XXX)";
 
     #[test]
     fn solve_example_part1() {
-        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT)), 6)
+        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT).unwrap()), Some(6))
     }
 
     #[test]
     fn solve_example_part2() {
-        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT_2)), 6)
+        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT_2).unwrap()), Ok(6))
+    }
+
+    const UNICODE_INPUT: &str = "RL
+
+start = (mid, ünïcødé)
+mid = (end, start)
+ünïcødé = (終, start)
+終 = (終, 終)
+end = (end, end)";
+
+    #[test]
+    fn parse_names_of_any_length() {
+        let (instructions, map) = parse_input(UNICODE_INPUT).unwrap();
+
+        assert_eq!(instructions, vec![Instruction::Right, Instruction::Left]);
+        assert_eq!(map.len(), 5);
+        assert_eq!(
+            map.get("ünïcødé"),
+            Some(&["終".to_string(), "start".to_string()])
+        );
+    }
+
+    #[test]
+    fn parse_input_with_trailing_newline() {
+        assert_eq!(
+            parse_input(&format!("{}\n", EXAMPLE_INPUT)).unwrap(),
+            parse_input(EXAMPLE_INPUT).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_invalid_network() {
+        assert_eq!(parse_input("LR\n\nAAA = (BBB CCC)"), Err("Invalid network"));
+        assert_eq!(
+            parse_input("LR\n\nAAA = (BBB, CCC)\nBBB = (AAA, AAA)"),
+            Err("Network leads to an undefined node")
+        );
+    }
+
+    #[test]
+    fn steps_with_custom_nodes() {
+        let input = parse_input(UNICODE_INPUT).unwrap();
+
+        assert_eq!(steps_to_end(&input, "start", |node| node == "終"), Some(2));
+        assert_eq!(
+            steps_to_end(&input, "start", |node| node == "ünïcødé"),
+            Some(1)
+        );
+        assert_eq!(
+            steps_to_end(&input, "start", |node| node == "nowhere"),
+            None
+        );
+        assert_eq!(steps_to_end(&input, "QQQ", |node| node == "終"), None);
+        assert_eq!(
+            ghost_steps(
+                &input,
+                |node| node == "start",
+                |node| node.chars().count() == 1
+            ),
+            Ok(2)
+        );
+        assert_eq!(
+            ghost_steps(&input, |node| node == "start", |_| false),
+            Err("Ghosts never meet on end nodes")
+        );
+    }
+
+    /// Ghost 1 is on an end node at every even step from 2, ghost 2 at steps 1, 4, 7, ... and
+    /// ghost 3 at steps 1 and 2 modulo 5. The LCM of the first end steps would give 2.
+    const UNALIGNED_INPUT: &str = "L
+
+1A = (1B, 1B)
+1B = (1Z, 1Z)
+1Z = (1C, 1C)
+1C = (1Z, 1Z)
+2A = (2Z, 2Z)
+2Z = (2B, 2B)
+2B = (2C, 2C)
+2C = (2Z, 2Z)
+3A = (3Z, 3Z)
+3Z = (3ZZ, 3ZZ)
+3ZZ = (3B, 3B)
+3B = (3C, 3C)
+3C = (3A, 3A)";
+
+    #[test]
+    fn test_graph() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+        let graph = Graph::new(&input);
+
+        assert_eq!(graph.names, vec!["AAA", "BBB", "ZZZ"]);
+        assert_eq!(graph.edges, vec![[1, 1], [0, 2], [2, 2]]);
+        assert_eq!(graph.jumps, vec![1, 2, 2]);
+        assert_eq!(
+            graph.first_ends(&graph.ends(|node| node == "ZZZ")),
+            vec![None, None, Some(0)]
+        );
+    }
+
+    #[test]
+    fn test_ghost_cycle() {
+        let input = parse_input(UNALIGNED_INPUT).unwrap();
+        let graph = Graph::new(&input);
+        let ends = graph.ends(|node| node.ends_with('Z'));
+        let ghost_cycle = |start| graph.ghost_cycle(graph.id(start).unwrap(), &ends);
+
+        assert_eq!(
+            ghost_cycle("1A"),
+            GhostCycle {
+                tail: 2,
+                cycle: 2,
+                end_steps: vec![2]
+            }
+        );
+        assert_eq!(
+            ghost_cycle("2A"),
+            GhostCycle {
+                tail: 1,
+                cycle: 3,
+                end_steps: vec![1]
+            }
+        );
+        assert_eq!(
+            ghost_cycle("3A"),
+            GhostCycle {
+                tail: 0,
+                cycle: 5,
+                end_steps: vec![1, 2]
+            }
+        );
+    }
+
+    #[test]
+    fn test_ghost_cycle_follows_instructions() {
+        let input = parse_input(EXAMPLE_INPUT_2).unwrap();
+        let graph = Graph::new(&input);
+        let ends = graph.ends(|node| node.ends_with('Z'));
+        let ghost_cycle = |start| graph.ghost_cycle(graph.id(start).unwrap(), &ends);
+
+        assert_eq!(
+            ghost_cycle("11A"),
+            GhostCycle {
+                tail: 1,
+                cycle: 2,
+                end_steps: vec![2]
+            }
+        );
+        assert_eq!(
+            ghost_cycle("22A"),
+            GhostCycle {
+                tail: 1,
+                cycle: 6,
+                end_steps: vec![3, 6]
+            }
+        );
+    }
+
+    #[test]
+    fn solve_unaligned_ghosts() {
+        let input = parse_input(UNALIGNED_INPUT).unwrap();
+
+        assert_eq!(solve_part2(&input), Ok(16));
+        assert_eq!(
+            ghost_steps(&input, |n| n == "1A" || n == "2A", |n| n.ends_with('Z')),
+            Ok(4)
+        );
+    }
+
+    #[test]
+    fn synchronise_in_tail() {
+        let cycles = [
+            GhostCycle {
+                tail: 5,
+                cycle: 1,
+                end_steps: vec![3, 5],
+            },
+            GhostCycle {
+                tail: 0,
+                cycle: 3,
+                end_steps: vec![0],
+            },
+        ];
+
+        assert_eq!(synchronise(&cycles), Ok(Some(3)));
+    }
+
+    #[test]
+    fn synchronise_never() {
+        let cycles = [
+            GhostCycle {
+                tail: 0,
+                cycle: 2,
+                end_steps: vec![0],
+            },
+            GhostCycle {
+                tail: 0,
+                cycle: 4,
+                end_steps: vec![1, 3],
+            },
+        ];
+
+        assert_eq!(synchronise(&cycles), Ok(None));
+    }
+
+    #[test]
+    fn synchronise_many_ghosts_with_several_end_steps() {
+        // 2^30 combinations of end steps, none of which line up
+        let mut cycles = (0..29)
+            .map(|_| GhostCycle {
+                tail: 0,
+                cycle: 4,
+                end_steps: vec![0, 1],
+            })
+            .collect_vec();
+        cycles.push(GhostCycle {
+            tail: 0,
+            cycle: 4,
+            end_steps: vec![2, 3],
+        });
+        assert_eq!(synchronise(&cycles), Ok(None));
+
+        cycles.push(GhostCycle {
+            tail: 0,
+            cycle: 6,
+            end_steps: vec![2, 5],
+        });
+        cycles[29].end_steps.push(1);
+        assert_eq!(synchronise(&cycles), Ok(Some(5)));
+    }
+
+    #[test]
+    fn synchronise_overflow() {
+        // coprime cycles whose product does not fit in a u128
+        let moduli = [
+            (1 << 61) - 1,
+            (1 << 31) - 1,
+            (1 << 19) - 1,
+            (1 << 17) - 1,
+            8191,
+        ];
+        let cycles = moduli
+            .iter()
+            .map(|&cycle| GhostCycle {
+                tail: 0,
+                cycle,
+                end_steps: vec![cycle - 1],
+            })
+            .collect_vec();
+
+        assert_eq!(synchronise(&cycles), Err(STEPS_OVERFLOW));
+        assert_eq!(
+            synchronise(&cycles[..4]),
+            Ok(Some(
+                ((1 << 61) - 1) * ((1 << 31) - 1) * ((1 << 19) - 1) * ((1 << 17) - 1) - 1
+            ))
+        );
+    }
+
+    #[test]
+    fn example_to_dot() {
+        assert_eq!(
+            to_dot(
+                &parse_input(EXAMPLE_INPUT).unwrap(),
+                |node| node == "AAA",
+                |node| node == "ZZZ",
+                false
+            ),
+            r#"digraph network {
+    "AAA" [style=filled, fillcolor="palegreen"];
+    "ZZZ" [style=filled, fillcolor="lightcoral"];
+    "AAA" -> "BBB" [label="L"];
+    "AAA" -> "BBB" [label="R"];
+    "BBB" -> "AAA" [label="L"];
+    "BBB" -> "ZZZ" [label="R"];
+    "ZZZ" -> "ZZZ" [label="L"];
+    "ZZZ" -> "ZZZ" [label="R"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn example_to_dot_with_cycles() {
+        let dot = to_dot(
+            &parse_input(EXAMPLE_INPUT_2).unwrap(),
+            |node| node.ends_with('A'),
+            |node| node.ends_with('Z'),
+            true,
+        );
+
+        // ghost 11A keeps walking 11Z -L-> 11B -R-> 11Z
+        assert!(dot.contains(r#""11Z" -> "11B" [label="L", color="red"];"#));
+        assert!(dot.contains(r#""11B" -> "11Z" [label="R", color="red"];"#));
+        assert!(dot.contains(r#""11A" -> "11B" [label="L"];"#));
+        // ghost 22A walks every edge of 22B, 22C and 22Z
+        assert!(dot.contains(r#""22B" -> "22C" [label="L", color="blue"];"#));
+        assert!(dot.contains(r#""22C" -> "22Z" [label="R", color="blue"];"#));
+        assert!(dot.contains(r#""22Z" -> "22B" [label="R", color="blue"];"#));
+        assert!(dot.contains(r#""XXX" -> "XXX" [label="L"];"#));
     }
 
     #[test]
-    fn test_vec_lcm() {
-        assert_eq!(lcm(&vec![4, 6]), 12);
+    fn to_dot_start_and_end_node() {
+        let dot = to_dot(
+            &parse_input(EXAMPLE_INPUT).unwrap(),
+            |_| true,
+            |node| node == "ZZZ",
+            false,
+        );
 
-        assert_eq!(lcm(&vec![4, 6, 5]), 60);
+        assert!(dot.contains(r#""ZZZ" [style=filled, fillcolor="palegreen:lightcoral"];"#));
     }
 }
@@ -1,56 +1,128 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-#[aoc_generator(day9)]
-fn parse_input(input: &str) -> Vec<Vec<i32>> {
-    input
-        .lines()
-        .map(|line| {
-            line.split_whitespace()
-                .map(|n| n.parse().expect("A valid number"))
-                .collect()
-        })
+use crate::math::gcd;
+
+const OVERFLOW: &str = "Value does not fit in an i128";
+const ILL_DEFINED: &str = "Differences never reach zero, so the sequence cannot be extrapolated";
+
+/// A sequence of OASIS readings, kept as the Newton forward-difference coefficients of the
+/// polynomial through it: the first value of every row of its difference table.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct OasisSequence {
+    coefficients: Vec<i128>,
+}
+
+impl OasisSequence {
+    pub fn new(values: &[i128]) -> Result<Self, &'static str> {
+        if values.is_empty() {
+            return Err("Cannot extrapolate an empty sequence");
+        }
+
+        let mut coefficients = Vec::with_capacity(values.len());
+        let mut row = values.to_vec();
+        while let Some(&first) = row.first() {
+            coefficients.push(first);
+            row = differences(&row).ok_or(OVERFLOW)?;
+        }
+
+        Ok(OasisSequence { coefficients })
+    }
+
+    /// Whether the difference table of the readings reaches a row of zeros, which is when the
+    /// readings follow a polynomial of degree below `len - 1` and extrapolating is well-defined.
+    pub fn is_well_defined(&self) -> bool {
+        self.coefficients.last() == Some(&0)
+    }
+
+    /// Degree of the polynomial through the readings, 0 for a constant sequence.
+    pub fn degree(&self) -> usize {
+        self.coefficients
+            .iter()
+            .rposition(|&coefficient| coefficient != 0)
+            .unwrap_or(0)
+    }
+
+    /// The value at `index`, where the readings are at `0..len`. Indices before the first
+    /// reading are negative.
+    pub fn value_at(&self, index: i128) -> Result<i128, &'static str> {
+        // sum of coefficient k times the binomial coefficient (index choose k)
+        let mut binomial: i128 = 1;
+        let mut value: i128 = 0;
+        for (k, &coefficient) in self.coefficients[..=self.degree()].iter().enumerate() {
+            if k > 0 {
+                let factor = index.checked_sub(k as i128 - 1).ok_or(OVERFLOW)?;
+                // the product is divisible by k, so dividing first keeps the intermediate as
+                // small as the result
+                let divisor = gcd(binomial.unsigned_abs(), k as u128) as i128;
+                binomial = (binomial / divisor)
+                    .checked_mul(factor / (k as i128 / divisor))
+                    .ok_or(OVERFLOW)?;
+            }
+            let term = coefficient.checked_mul(binomial).ok_or(OVERFLOW)?;
+            value = value.checked_add(term).ok_or(OVERFLOW)?;
+        }
+        Ok(value)
+    }
+
+    /// The value right after the last reading.
+    pub fn next_value(&self) -> Result<i128, &'static str> {
+        self.value_at(self.coefficients.len() as i128)
+    }
+
+    /// The value right before the first reading.
+    pub fn previous_value(&self) -> Result<i128, &'static str> {
+        self.value_at(-1)
+    }
+}
+
+fn parse_line(line: &str) -> Result<Vec<i128>, &'static str> {
+    line.split_whitespace()
+        .map(|n| n.parse().map_err(|_| "Failed to parse number"))
         .collect()
 }
 
+/// Parses a line of readings, rejecting sequences that cannot be extrapolated.
+pub fn parse_sequence(line: &str) -> Result<OasisSequence, &'static str> {
+    let sequence = OasisSequence::new(&parse_line(line)?)?;
+    match sequence.is_well_defined() {
+        true => Ok(sequence),
+        false => Err(ILL_DEFINED),
+    }
+}
+
+/// Checks every line of the input, giving the sequence for a line that can be extrapolated and
+/// why it cannot otherwise.
+pub fn validate(input: &str) -> Vec<Result<OasisSequence, &'static str>> {
+    input.lines().map(parse_sequence).collect()
+}
+
+#[aoc_generator(day9)]
+fn parse_input(input: &str) -> Result<Vec<OasisSequence>, &'static str> {
+    validate(input).into_iter().collect()
+}
+
 #[aoc(day9, part1)]
-fn solve_part1(input: &[Vec<i32>]) -> i32 {
+fn solve_part1(input: &[OasisSequence]) -> i128 {
     input
         .iter()
-        .map(|numbers| extrapolate_forwards(numbers))
+        .map(|sequence| sequence.next_value().expect("Extrapolated value fits"))
         .sum()
 }
 
 #[aoc(day9, part2)]
-fn solve_part2(input: &[Vec<i32>]) -> i32 {
+fn solve_part2(input: &[OasisSequence]) -> i128 {
     input
         .iter()
-        .map(|numbers| extrapolate_backwards(numbers))
+        .map(|sequence| sequence.previous_value().expect("Extrapolated value fits"))
         .sum()
 }
 
-fn extrapolate_forwards(numbers: &Vec<i32>) -> i32 {
-    if numbers.iter().all(|&n| n == 0) {
-        0
-    } else {
-        numbers.last().expect("Cannot extrapolate empty vec")
-            + extrapolate_forwards(&differences(numbers))
-    }
-}
-
-fn extrapolate_backwards(numbers: &Vec<i32>) -> i32 {
-    if numbers.iter().all(|&n| n == 0) {
-        0
-    } else {
-        numbers.first().expect("Cannot extrapolate empty vec")
-            - extrapolate_backwards(&differences(numbers))
-    }
-}
-
-fn differences(numbers: &Vec<i32>) -> Vec<i32> {
+/// Differences between consecutive numbers, `None` when one does not fit in an `i128`.
+fn differences(numbers: &[i128]) -> Option<Vec<i128>> {
     numbers
         .iter()
         .zip(numbers.iter().skip(1))
-        .map(|(a, b)| b - a)
+        .map(|(a, b)| b.checked_sub(*a))
         .collect()
 }
 
@@ -62,14 +134,18 @@ mod tests {
 1 3 6 10 15 21
 10 13 16 21 30 45";
 
+    fn sequence(values: &[i128]) -> OasisSequence {
+        OasisSequence::new(values).unwrap()
+    }
+
     #[test]
     fn parse_example() {
         assert_eq!(
-            parse_input(EXAMPLE_INPUT),
+            parse_input(EXAMPLE_INPUT).unwrap(),
             vec![
-                vec![0, 3, 6, 9, 12, 15],
-                vec![1, 3, 6, 10, 15, 21],
-                vec![10, 13, 16, 21, 30, 45],
+                sequence(&[0, 3, 6, 9, 12, 15]),
+                sequence(&[1, 3, 6, 10, 15, 21]),
+                sequence(&[10, 13, 16, 21, 30, 45]),
             ]
         )
     }
@@ -77,23 +153,146 @@ mod tests {
     #[test]
     fn test_vec_differences() {
         assert_eq!(
-            differences(&parse_input(EXAMPLE_INPUT)[0]),
-            vec![3, 3, 3, 3, 3]
-        )
+            differences(&[0, 3, 6, 9, 12, 15]),
+            Some(vec![3, 3, 3, 3, 3])
+        );
+        assert_eq!(differences(&[i128::MIN, i128::MAX]), None);
     }
 
     #[test]
     fn test_extrapolate() {
-        assert_eq!(extrapolate_forwards(&vec![0, 0, 0]), 0)
+        let zeros = sequence(&[0, 0, 0]);
+
+        assert_eq!(zeros.next_value(), Ok(0));
+        assert_eq!(zeros.previous_value(), Ok(0));
+        assert_eq!(zeros.degree(), 0);
+    }
+
+    #[test]
+    fn example_sequences() {
+        let sequences = parse_input(EXAMPLE_INPUT).unwrap();
+
+        let degrees: Vec<usize> = sequences.iter().map(OasisSequence::degree).collect();
+        let next: Vec<i128> = sequences.iter().map(|s| s.next_value().unwrap()).collect();
+        let previous: Vec<i128> = sequences
+            .iter()
+            .map(|s| s.previous_value().unwrap())
+            .collect();
+
+        assert_eq!(degrees, vec![1, 2, 3]);
+        assert_eq!(next, vec![18, 28, 68]);
+        assert_eq!(previous, vec![-3, 0, 5]);
+    }
+
+    #[test]
+    fn values_at_readings() {
+        for line in EXAMPLE_INPUT.lines() {
+            let values = parse_line(line).unwrap();
+            let sequence = sequence(&values);
+
+            for (index, &value) in values.iter().enumerate() {
+                assert_eq!(sequence.value_at(index as i128), Ok(value));
+            }
+        }
+    }
+
+    #[test]
+    fn values_far_away() {
+        // (n + 1)(n + 2) / 2
+        let triangular = sequence(&[1, 3, 6, 10, 15, 21]);
+        for n in [
+            100,
+            -100,
+            1_000_000_000_000_000_000,
+            -1_000_000_000_000_000_000,
+        ] {
+            assert_eq!(triangular.value_at(n), Ok((n + 1) * (n + 2) / 2), "{}", n);
+        }
+
+        let cubes = sequence(&[0, 1, 8, 27, 64]);
+        assert_eq!(cubes.degree(), 3);
+        assert_eq!(cubes.value_at(-(1 << 40)), Ok(-(1 << 120)));
+    }
+
+    #[test]
+    fn values_that_overflow() {
+        let squares = sequence(&[0, 1, 4, 9]);
+
+        assert_eq!(squares.value_at(1 << 62), Ok(1 << 124));
+        // (n choose 2) fits although n (n - 1) does not
+        let n: i128 = 3 << 62;
+        assert_eq!(
+            sequence(&[1, 3, 6, 10]).value_at(n),
+            Ok((n + 1) * ((n + 2) / 2))
+        );
+        assert_eq!(sequence(&[0, 0, 1, 3]).value_at(n), Ok(n / 2 * (n - 1)));
+        assert_eq!(squares.value_at(1 << 64), Err(OVERFLOW));
+        assert_eq!(squares.value_at(i128::MIN), Err(OVERFLOW));
+        assert_eq!(
+            sequence(&[i128::MAX - 1, i128::MAX]).next_value(),
+            Err(OVERFLOW)
+        );
+        assert_eq!(OasisSequence::new(&[i128::MAX, i128::MIN]), Err(OVERFLOW));
+    }
+
+    #[test]
+    fn constant_and_short_sequences() {
+        assert_eq!(sequence(&[5, 5, 5]).degree(), 0);
+        assert_eq!(sequence(&[5, 5, 5]).value_at(-1_000), Ok(5));
+        assert_eq!(sequence(&[0]).next_value(), Ok(0));
+        assert_eq!(sequence(&[7]).next_value(), Ok(7));
+        assert_eq!(
+            OasisSequence::new(&[]),
+            Err("Cannot extrapolate an empty sequence")
+        );
+    }
+
+    #[test]
+    fn well_defined_sequences() {
+        assert!(sequence(&[0, 3, 6, 9, 12, 15]).is_well_defined());
+        assert!(sequence(&[1, 2, 4, 7]).is_well_defined());
+        assert!(sequence(&[5, 5]).is_well_defined());
+        assert!(sequence(&[0]).is_well_defined());
+
+        assert!(!sequence(&[1, 2, 4, 8]).is_well_defined());
+        assert!(!sequence(&[5, 6]).is_well_defined());
+        assert!(!sequence(&[7]).is_well_defined());
+    }
+
+    #[test]
+    fn validate_lines() {
+        let report = validate("0 3 6 9 12 15\n1 2 4 8\n7\n\n1 x 3\n0 0");
+
+        assert_eq!(
+            report,
+            vec![
+                Ok(sequence(&[0, 3, 6, 9, 12, 15])),
+                Err(ILL_DEFINED),
+                Err(ILL_DEFINED),
+                Err("Cannot extrapolate an empty sequence"),
+                Err("Failed to parse number"),
+                Ok(sequence(&[0, 0])),
+            ]
+        );
+        assert_eq!(report[0].as_ref().map(OasisSequence::degree), Ok(1));
+    }
+
+    #[test]
+    fn reject_ill_defined_input() {
+        assert_eq!(parse_input("0 3 6 9\n1 2 4 8"), Err(ILL_DEFINED));
+        assert_eq!(
+            parse_input(EXAMPLE_INPUT).map(|sequences| sequences.len()),
+            Ok(3)
+        );
     }
 
     #[test]
     fn solve_example_part1() {
-        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT)), 114)
+        assert_eq!(solve_part1(&parse_input(EXAMPLE_INPUT).unwrap()), 114)
     }
 
     #[test]
     fn solve_example_part2() {
-        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT)), 2)
+        assert_eq!(solve_part2(&parse_input(EXAMPLE_INPUT).unwrap()), 2)
     }
 }
@@ -1,22 +1,25 @@
 use aoc_runner_derive::aoc_lib;
 
-mod day1;
+pub mod day1;
 mod day10;
 mod day11;
-mod day12;
-mod day2;
-mod day3;
+pub mod day12;
+pub mod day2;
+pub mod day3;
 mod day4;
 mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-mod day13;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
+pub mod day13;
 mod day14;
-mod day15;
+pub mod day15;
 mod day16;
 mod day17;
 mod day18;
+pub mod math;
+#[cfg(test)]
+mod test_utils;
 
 aoc_lib! {year = 2023}
@@ -0,0 +1,260 @@
+//! Number theory helpers shared between the days.
+//!
+//! Everything works on `u128` and reports overflow as `None` instead of wrapping.
+
+pub fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple, `None` when it does not fit in a `u128`.
+pub fn lcm(a: u128, b: u128) -> Option<u128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Least common multiple of all numbers, which is 1 for an empty slice.
+pub fn lcm_all(nums: &[u128]) -> Option<u128> {
+    nums.iter().try_fold(1, |acc, &n| lcm(acc, n))
+}
+
+/// Returns `(gcd, x, y)` such that `a * x + b * y = gcd`.
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+
+    match old_r < 0 {
+        true => (-old_r, -old_x, -old_y),
+        false => (old_r, old_x, old_y),
+    }
+}
+
+/// `(a + b) % m` for `a, b < m`, without overflowing.
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    match a >= m - b {
+        true => a - (m - b),
+        false => a + b,
+    }
+}
+
+/// `(a - b) % m` for `a, b < m`.
+fn sub_mod(a: u128, b: u128, m: u128) -> u128 {
+    match a >= b {
+        true => a - b,
+        false => m - (b - a),
+    }
+}
+
+/// `(a * b) % m` by doubling and adding, so it cannot overflow.
+fn mul_mod(a: u128, mut b: u128, m: u128) -> u128 {
+    let mut a = a % m;
+    let mut result = 0;
+    b %= m;
+
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b >>= 1;
+    }
+    result
+}
+
+/// The `x` in `0..m` with `a * x ≡ 1 (mod m)`, if `a` and `m` are coprime.
+pub fn mod_inverse(a: u128, m: u128) -> Option<u128> {
+    if m == 0 {
+        return None;
+    }
+
+    let (mut old_r, mut r) = (a % m, m);
+    let (mut old_s, mut s) = (1 % m, 0);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, sub_mod(old_s, mul_mod(q, s, m), m));
+    }
+
+    (old_r == 1).then_some(old_s)
+}
+
+/// Solves a system of `x ≡ remainder (mod modulus)` congruences. The moduli do not need to be
+/// coprime, but do need to be positive.
+///
+/// Returns the smallest solution together with the combined modulus, or `None` when the
+/// congruences contradict each other or the combined modulus does not fit in a `u128`.
+pub fn crt(congruences: &[(u128, u128)]) -> Option<(u128, u128)> {
+    congruences.iter().try_fold((0, 1), |(a1, m1), &(a2, m2)| {
+        if m2 == 0 {
+            return None;
+        }
+        let a2 = a2 % m2;
+
+        let g = gcd(m1, m2);
+        if a1 % g != a2 % g {
+            return None;
+        }
+
+        // a1 + m1 * k ≡ a2 (mod m2), solved for k modulo m2 / g
+        let m2_g = m2 / g;
+        let modulus = m1.checked_mul(m2_g)?;
+        let diff = sub_mod((a2 / g) % m2_g, (a1 / g) % m2_g, m2_g);
+        let k = mul_mod(diff, mod_inverse(m1 / g, m2_g)?, m2_g);
+
+        Some((a1 + m1 * k, modulus))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::xorshift;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(gcd(5, 0), 5);
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(gcd(u128::MAX, u128::MAX - 1), 1);
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(0, 6), Some(0));
+        assert_eq!(lcm(u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(lcm(u128::MAX, u128::MAX - 1), None);
+    }
+
+    #[test]
+    fn test_lcm_all() {
+        assert_eq!(lcm_all(&[4, 6]), Some(12));
+        assert_eq!(lcm_all(&[4, 6, 5]), Some(60));
+        assert_eq!(lcm_all(&[]), Some(1));
+        assert_eq!(lcm_all(&[1 << 100, 3 << 100, 5]), Some(15 << 100));
+        assert_eq!(
+            lcm_all(&[1 << 100, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        assert_eq!(extended_gcd(240, 46), (2, -9, 47));
+        assert_eq!(extended_gcd(0, 0), (0, 1, 0));
+        assert_eq!(extended_gcd(-4, 6).0, 2);
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(17, 256), Some(241));
+        assert_eq!(mod_inverse(2, 4), None);
+        assert_eq!(mod_inverse(5, 1), Some(0));
+        assert_eq!(mod_inverse(5, 0), None);
+        assert_eq!(mod_inverse(2, u128::MAX), Some(u128::MAX / 2 + 1));
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(0, 2), (1, 3), (1, 5)]), Some((16, 30)));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(0, 4), (1, 6)]), None);
+        assert_eq!(crt(&[(1, 0)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(1 << 126, 1 << 127), (1, 3)]), None);
+    }
+
+    #[test]
+    fn properties_gcd_lcm() {
+        for a in 0..60u128 {
+            for b in 0..60u128 {
+                let g = gcd(a, b);
+                let (eg, x, y) = extended_gcd(a as i128, b as i128);
+
+                assert_eq!(eg, g as i128);
+                assert_eq!(a as i128 * x + b as i128 * y, g as i128);
+
+                if a > 0 && b > 0 {
+                    let l = lcm(a, b).unwrap();
+                    assert_eq!((a % g, b % g, l % a, l % b), (0, 0, 0, 0));
+                    assert_eq!(g * l, a * b);
+                    assert_eq!((1..=l).find(|n| n % a == 0 && n % b == 0), Some(l));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn properties_mod_inverse() {
+        for m in 1..80u128 {
+            for a in 0..2 * m {
+                match mod_inverse(a, m) {
+                    Some(x) => assert_eq!((x < m, a * x % m), (true, 1 % m), "{} {}", a, m),
+                    None => assert_ne!(gcd(a, m), 1, "{} {}", a, m),
+                }
+            }
+        }
+
+        let mut rng = xorshift(39);
+        for _ in 0..1000 {
+            let m = ((rng() as u128) << 64 | rng() as u128) | 1;
+            let a = (rng() as u128) << 64 | rng() as u128;
+            if let Some(x) = mod_inverse(a, m) {
+                assert_eq!(mul_mod(a, x, m), 1, "{} {}", a, m);
+            }
+        }
+    }
+
+    #[test]
+    fn properties_crt() {
+        for m1 in 1..13u128 {
+            for m2 in 1..13u128 {
+                for a1 in 0..m1 {
+                    for a2 in 0..m2 {
+                        let expected = (0..m1 * m2).find(|x| x % m1 == a1 && x % m2 == a2);
+                        assert_eq!(
+                            crt(&[(a1, m1), (a2, m2)]).map(|(x, _)| x),
+                            expected,
+                            "{} {} {} {}",
+                            a1,
+                            m1,
+                            a2,
+                            m2
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut rng = xorshift(2023);
+        for _ in 0..1000 {
+            let congruences: Vec<(u128, u128)> = (0..3)
+                .map(|_| {
+                    let m = (rng() >> 32) as u128 + 1;
+                    (rng() as u128 % m, m)
+                })
+                .collect();
+
+            if let Some((x, modulus)) = crt(&congruences) {
+                assert!(x < modulus);
+                assert!(congruences.iter().all(|&(a, m)| x % m == a));
+            }
+        }
+    }
+}
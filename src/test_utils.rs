@@ -0,0 +1,11 @@
+//! Helpers shared by the tests of several days.
+
+/// Deterministic xorshift generator so randomised tests are reproducible.
+pub fn xorshift(mut state: u64) -> impl FnMut() -> u64 {
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}